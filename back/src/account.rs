@@ -0,0 +1,108 @@
+use crate::storage::{Db, Storage};
+use argon2::{
+    password_hash::{
+        rand_core::{OsRng, RngCore as _},
+        PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString,
+    },
+    Argon2,
+};
+use shared::{AccountError, Credentials, Session};
+
+pub const USERNAME_LEN: std::ops::RangeInclusive<usize> = 3..=20;
+pub const PASSWORD_LEN: std::ops::RangeInclusive<usize> = 8..=128;
+// Sessions have to log in again after that
+pub const SESSION_SECONDS: i64 = 30 * 24 * 60 * 60;
+// Checked against when the username does not exist, so it takes as long as a wrong password
+const DUMMY_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$DdJIGUkdrqt0NQBayfdG8w$cEpYi17ycQoHX+7NzVfXBKOtEIPicnCJ00QFKPXuxoo";
+
+fn internal(e: impl std::fmt::Display) -> AccountError {
+    error!("Account storage failed: {e}");
+    AccountError::Internal
+}
+
+pub fn validate(credentials: &Credentials) -> Result<(), AccountError> {
+    let username = &credentials.username;
+    if !USERNAME_LEN.contains(&username.chars().count())
+        || !username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(AccountError::InvalidUsername);
+    }
+
+    if !PASSWORD_LEN.contains(&credentials.password.chars().count()) {
+        return Err(AccountError::InvalidPassword);
+    }
+
+    Ok(())
+}
+
+// 256 random bits, as hex
+pub fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn new_session(storage: &Storage, player_id: u128) -> Result<Session, AccountError> {
+    let token = new_token();
+    storage
+        .add_session(&token, player_id, crate::storage::now() + SESSION_SECONDS)
+        .map_err(internal)?;
+
+    Ok(Session { player_id, token })
+}
+
+// Register and login hash for a while, they block and only lock the database for the queries
+// Creates the account and logs it in
+pub fn register(db: &Db, credentials: &Credentials) -> Result<Session, AccountError> {
+    validate(credentials)?;
+
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(credentials.password.as_bytes(), &salt)
+        .map_err(internal)?
+        .to_string();
+
+    let player_id = ((OsRng.next_u64() as u128) << 64) | OsRng.next_u64() as u128;
+    let storage = db.blocking_lock();
+    if !storage
+        .add_account(player_id, &credentials.username, &hash)
+        .map_err(internal)?
+    {
+        return Err(AccountError::UsernameTaken);
+    }
+
+    new_session(&storage, player_id)
+}
+
+pub fn login(db: &Db, credentials: &Credentials) -> Result<Session, AccountError> {
+    let account = db
+        .blocking_lock()
+        .account(&credentials.username)
+        .map_err(internal)?;
+    let (player_id, hash) = match account {
+        Some((player_id, hash)) => (Some(player_id), hash),
+        None => (None, DUMMY_HASH.to_string()),
+    };
+
+    let hash = PasswordHash::new(&hash).map_err(internal)?;
+    let verified = Argon2::default()
+        .verify_password(credentials.password.as_bytes(), &hash)
+        .is_ok();
+    let (Some(player_id), true) = (player_id, verified) else {
+        return Err(AccountError::WrongCredentials);
+    };
+
+    new_session(&db.blocking_lock(), player_id)
+}
+
+// Returns false if the token was not a session
+pub fn logout(storage: &Storage, token: &str) -> Result<bool, AccountError> {
+    storage.remove_session(token).map_err(internal)
+}
+
+// The player logged in with this token
+pub fn authenticate(storage: &Storage, token: &str) -> Option<u128> {
+    storage.session(token).map_err(internal).ok().flatten()
+}
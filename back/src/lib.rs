@@ -1,22 +1,60 @@
 #[macro_use(trace, debug, info, warn, error)]
 extern crate log;
 
+pub mod account;
 pub mod catchers;
+pub mod profile;
+pub mod queue;
+pub mod rating;
 pub mod response;
+pub mod room;
 pub mod routes;
+pub mod session;
+pub mod storage;
 
 // Needed for tests
 pub async fn build_rocket() -> rocket::Rocket<rocket::Ignite> {
     let user_map = routes::UserMap::default();
+    let room_map = room::RoomMap::default();
 
-    rocket::build()
+    let rocket = rocket::build();
+    // Seconds before a quick play match starts with fewer players than asked for
+    let queue_wait = rocket
+        .figment()
+        .extract_inner::<u64>("queue_wait")
+        .map(std::time::Duration::from_secs)
+        .unwrap_or(queue::DEFAULT_WAIT);
+    let queue = queue::SharedQueue::new(tokio::sync::Mutex::new(queue::Queue::new(queue_wait)));
+
+    // Migrated when opened, `:memory:` keeps everything in memory, that's what the tests use
+    let database = rocket
+        .figment()
+        .extract_inner::<String>("database")
+        .unwrap_or(storage::DEFAULT_PATH.to_string());
+    let storage = storage::Storage::open(&database)
+        .unwrap_or_else(|e| panic!("Failed to open the database at {database}: {e}"));
+    let ratings = storage.ratings().unwrap_or_else(|e| {
+        error!("Failed to load the ratings: {e}");
+        Default::default()
+    });
+    if let Err(e) = storage.remove_expired_sessions() {
+        error!("Failed to remove the expired sessions: {e}");
+    }
+
+    rocket
         .manage(user_map)
+        .manage(room_map)
+        .manage(queue)
+        .manage(rating::RatingMap::new(tokio::sync::Mutex::new(ratings)))
+        .manage(storage::Db::new(tokio::sync::Mutex::new(storage)))
         .register("/", rocket::catchers![catchers::root_404])
         .mount(
             "/",
             rocket::routes![
                 routes::root,
                 routes::home,
+                routes::settings,
+                routes::leaderboard_page,
                 routes::_404,
                 routes::front_js,
                 routes::front_bg_wasm,
@@ -30,6 +68,10 @@ pub async fn build_rocket() -> rocket::Rocket<rocket::Ignite> {
                 routes::robots_txt,
                 routes::ws_join,
                 routes::ws_broadcast,
+                routes::leaderboard,
+                routes::register,
+                routes::login,
+                routes::logout,
                 // Theses routes are troll routes, made to fuck with the bots
                 routes::bot_env,
                 routes::bot_admin,
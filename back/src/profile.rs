@@ -0,0 +1,70 @@
+use shared::Identicon;
+pub use shared::NameError;
+
+pub const NAME_LEN: std::ops::RangeInclusive<usize> = 3..=16;
+
+// Matched anywhere in a name, once it's lowercased, un-leeted and without separators
+// Short words that show up inside ordinary ones, like "ass" or "hell", are left out
+const BLOCKED_WORDS: &[&str] = &[
+    "fuck", "shit", "bitch", "cunt", "nigger", "nigga", "faggot", "whore", "slut", "retard",
+    "rapist", "nazi", "hitler", "penis", "vagina", "dildo", "porn",
+];
+
+fn is_profane(name: &str) -> bool {
+    let normalized = name
+        .chars()
+        .filter_map(|c| match c.to_ascii_lowercase() {
+            '0' => Some('o'),
+            '1' | '!' => Some('i'),
+            '3' => Some('e'),
+            '4' | '@' => Some('a'),
+            '5' | '$' => Some('s'),
+            '7' => Some('t'),
+            c if c.is_ascii_alphabetic() => Some(c),
+            // "f_u c-k" is still caught
+            _ => None,
+        })
+        .collect::<String>();
+
+    BLOCKED_WORDS.iter().any(|word| normalized.contains(word))
+}
+
+// The name as it will be shown, trimmed with its inner spaces collapsed
+pub fn validate_name(name: &str) -> Result<String, NameError> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if !NAME_LEN.contains(&name.chars().count()) {
+        return Err(NameError::Length);
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '_' || c == '-')
+    {
+        return Err(NameError::Characters);
+    }
+    if is_profane(&name) {
+        return Err(NameError::Profanity);
+    }
+
+    Ok(name)
+}
+
+// For guests that did not pick a name
+pub fn guest_name(user_id: u128) -> String {
+    format!("Guest-{:04}", user_id % 10_000)
+}
+
+// Same id, same identicon, so a player is recognizable from one match to the next
+pub fn identicon(user_id: u128) -> Identicon {
+    // splitmix64, ids of guests are random but not the ones of the tests
+    let mut hash = (user_id as u64) ^ ((user_id >> 64) as u64);
+    hash = hash.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^= hash >> 31;
+
+    Identicon {
+        hue: (hash % 360) as u16,
+        cells: (hash >> 32) as u16 & 0x7fff,
+    }
+}
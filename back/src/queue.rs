@@ -0,0 +1,191 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+// How long the oldest player of a queue waits before a match starts with fewer players
+// For ranked it's how long before the rating difference does not matter anymore
+pub const DEFAULT_WAIT: Duration = Duration::from_secs(30);
+// Rating difference accepted for a ranked match right away, it grows with the time waited
+const RATING_WINDOW: f64 = 100.;
+const RATING_WINDOW_GROWTH: f64 = 20.;
+
+pub type SharedQueue = Arc<Mutex<Queue>>;
+
+#[derive(Debug, Clone, Copy)]
+struct Ticket {
+    user_id: u128,
+    // Players wanted in the match, 2 for a 1v1
+    players: usize,
+    // Only for ranked tickets
+    rating: Option<f64>,
+    since: Instant,
+}
+
+#[derive(Debug)]
+pub struct Queue {
+    // Oldest first
+    tickets: Vec<Ticket>,
+    wait: Duration,
+    matches: u64,
+}
+
+impl Queue {
+    pub fn new(wait: Duration) -> Self {
+        Self {
+            tickets: Vec::new(),
+            wait,
+            matches: 0,
+        }
+    }
+
+    pub fn wait(&self) -> Duration {
+        self.wait
+    }
+    pub fn len(&self) -> usize {
+        self.tickets.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tickets.is_empty()
+    }
+    pub fn contains(&self, user_id: u128) -> bool {
+        self.tickets.iter().any(|ticket| ticket.user_id == user_id)
+    }
+
+    // Queueing again replaces the previous ticket, the player goes back to the end
+    // `players` is clamped like a room's capacity
+    pub fn join(&mut self, user_id: u128, players: usize, now: Instant) -> usize {
+        let players = players.clamp(crate::room::MIN_CAPACITY, crate::room::MAX_CAPACITY);
+
+        self.leave(user_id);
+        self.tickets.push(Ticket {
+            user_id,
+            players,
+            rating: None,
+            since: now,
+        });
+        players
+    }
+
+    pub fn join_ranked(&mut self, user_id: u128, rating: f64, now: Instant) {
+        self.leave(user_id);
+        self.tickets.push(Ticket {
+            user_id,
+            players: 2,
+            rating: Some(rating),
+            since: now,
+        });
+    }
+
+    // Returns false if the user was not queued
+    pub fn leave(&mut self, user_id: u128) -> bool {
+        let len = self.tickets.len();
+        self.tickets.retain(|ticket| ticket.user_id != user_id);
+        self.tickets.len() != len
+    }
+
+    // Takes the groups of players that can start a match out of the queue, oldest first
+    // A group is full, or has waited long enough and has at least two players
+    pub fn poll(&mut self, now: Instant) -> Vec<Vec<u128>> {
+        let mut sizes = self
+            .tickets
+            .iter()
+            .filter(|ticket| ticket.rating.is_none())
+            .map(|ticket| ticket.players)
+            .collect::<Vec<_>>();
+        sizes.sort_unstable();
+        sizes.dedup();
+
+        let mut groups = Vec::new();
+        for size in sizes {
+            loop {
+                let queued = self
+                    .tickets
+                    .iter()
+                    .filter(|ticket| ticket.rating.is_none() && ticket.players == size)
+                    .collect::<Vec<_>>();
+
+                let Some(oldest) = queued.first() else {
+                    break;
+                };
+                let waited = now.saturating_duration_since(oldest.since) >= self.wait;
+                if queued.len() < size && !(waited && queued.len() >= crate::room::MIN_CAPACITY) {
+                    break;
+                }
+
+                let group = queued
+                    .iter()
+                    .take(size)
+                    .map(|ticket| ticket.user_id)
+                    .collect::<Vec<_>>();
+                self.tickets
+                    .retain(|ticket| !group.contains(&ticket.user_id));
+                groups.push(group);
+            }
+        }
+
+        groups
+    }
+
+    // Rating difference the ticket accepts, it grows with the time waited
+    fn window(&self, ticket: &Ticket, now: Instant) -> f64 {
+        let waited = now.saturating_duration_since(ticket.since);
+        if waited >= self.wait {
+            f64::INFINITY
+        } else {
+            RATING_WINDOW + RATING_WINDOW_GROWTH * waited.as_secs_f64()
+        }
+    }
+
+    // Pairs ranked players, the oldest ticket first, with the closest rating it accepts
+    // Both have to accept the difference, a player that just queued is not thrown at anyone that waited long
+    pub fn poll_ranked(&mut self, now: Instant) -> Vec<[u128; 2]> {
+        let mut pairs = Vec::new();
+
+        let mut i = 0;
+        while i < self.tickets.len() {
+            let ticket = self.tickets[i];
+            let Some(rating) = ticket.rating else {
+                i += 1;
+                continue;
+            };
+            let window = self.window(&ticket, now);
+
+            let opponent = self
+                .tickets
+                .iter()
+                .skip(i + 1)
+                .filter_map(|other| {
+                    let difference = (other.rating? - rating).abs();
+                    (difference <= window.min(self.window(other, now)))
+                        .then_some((other.user_id, difference))
+                })
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+            match opponent {
+                Some((opponent, _)) => {
+                    self.tickets.retain(|other| {
+                        other.user_id != ticket.user_id && other.user_id != opponent
+                    });
+                    pairs.push([ticket.user_id, opponent]);
+                }
+                None => i += 1,
+            }
+        }
+
+        pairs
+    }
+
+    // Name of the room for the next match, unique among quick play rooms
+    pub fn next_room_name(&mut self) -> String {
+        self.matches += 1;
+        format!("Quick play #{}", self.matches)
+    }
+}
+
+impl Default for Queue {
+    fn default() -> Self {
+        Self::new(DEFAULT_WAIT)
+    }
+}
@@ -0,0 +1,157 @@
+use std::{collections::HashMap, f64::consts::PI, sync::Arc};
+use tokio::sync::Mutex;
+
+// Glicko-2, see http://www.glicko.net/glicko/glicko2.pdf
+// Every ranked match is its own rating period
+
+// Converts between the Glicko scale (1500 ± 350) and the Glicko-2 one (0 ± 2)
+const SCALE: f64 = 173.7178;
+const DEFAULT_RATING: f64 = 1500.;
+const DEFAULT_DEVIATION: f64 = 350.;
+const DEFAULT_VOLATILITY: f64 = 0.06;
+// How much the volatility can change, the paper suggests between 0.3 and 1.2
+const TAU: f64 = 0.5;
+const EPSILON: f64 = 0.000_001;
+
+// Entries in the leaderboard updates
+pub const LEADERBOARD_SIZE: usize = 50;
+
+pub type RatingMap = Arc<Mutex<HashMap<u128, Rating>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rating {
+    pub rating: f64,
+    // The rating is 95% sure to be within twice this
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+fn g(phi: f64) -> f64 {
+    1. / (1. + 3. * phi * phi / (PI * PI)).sqrt()
+}
+
+fn expected(mu: f64, mu_j: f64, phi_j: f64) -> f64 {
+    1. / (1. + (-g(phi_j) * (mu - mu_j)).exp())
+}
+
+impl Rating {
+    pub fn new(rating: f64, deviation: f64, volatility: f64) -> Self {
+        Self {
+            rating,
+            deviation,
+            volatility,
+        }
+    }
+
+    // The rating after a period against these opponents, the score is 1 for a win, 0.5 for a draw and 0 for a loss
+    pub fn update(&self, results: &[(Rating, f64)]) -> Self {
+        let mu = (self.rating - DEFAULT_RATING) / SCALE;
+        let phi = self.deviation / SCALE;
+
+        // Didn't play, only gets less sure
+        if results.is_empty() {
+            let phi = (phi * phi + self.volatility * self.volatility).sqrt();
+            return Self::new(self.rating, phi * SCALE, self.volatility);
+        }
+
+        let (variance, improvement) =
+            results
+                .iter()
+                .fold((0., 0.), |(variance, improvement), (opponent, score)| {
+                    let mu_j = (opponent.rating - DEFAULT_RATING) / SCALE;
+                    let phi_j = opponent.deviation / SCALE;
+                    let e = expected(mu, mu_j, phi_j);
+
+                    (
+                        variance + g(phi_j).powi(2) * e * (1. - e),
+                        improvement + g(phi_j) * (score - e),
+                    )
+                });
+        let v = 1. / variance;
+        let delta = v * improvement;
+
+        let sigma = self.new_volatility(phi, v, delta);
+
+        let phi_star = (phi * phi + sigma * sigma).sqrt();
+        let phi = 1. / (1. / (phi_star * phi_star) + 1. / v).sqrt();
+        let mu = mu + phi * phi * improvement;
+
+        Self::new(mu * SCALE + DEFAULT_RATING, phi * SCALE, sigma)
+    }
+
+    // Step 5 of the paper, the Illinois algorithm
+    fn new_volatility(&self, phi: f64, v: f64, delta: f64) -> f64 {
+        let a = (self.volatility * self.volatility).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            ex * (delta * delta - phi * phi - v - ex) / (2. * (phi * phi + v + ex).powi(2))
+                - (x - a) / (TAU * TAU)
+        };
+
+        let mut big_a = a;
+        let mut big_b = if delta * delta > phi * phi + v {
+            (delta * delta - phi * phi - v).ln()
+        } else {
+            let mut k = 1.;
+            while f(a - k * TAU) < 0. {
+                k += 1.;
+            }
+            a - k * TAU
+        };
+
+        let mut f_a = f(big_a);
+        let mut f_b = f(big_b);
+        while (big_b - big_a).abs() > EPSILON {
+            let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+            let f_c = f(big_c);
+
+            if f_c * f_b <= 0. {
+                big_a = big_b;
+                f_a = f_b;
+            } else {
+                f_a /= 2.;
+            }
+            big_b = big_c;
+            f_b = f_c;
+        }
+
+        (big_a / 2.).exp()
+    }
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self::new(DEFAULT_RATING, DEFAULT_DEVIATION, DEFAULT_VOLATILITY)
+    }
+}
+
+// Updates both players after a ranked 1v1
+pub fn rate_match(ratings: &mut HashMap<u128, Rating>, winner: u128, loser: u128) {
+    let winner_rating = ratings.get(&winner).copied().unwrap_or_default();
+    let loser_rating = ratings.get(&loser).copied().unwrap_or_default();
+
+    ratings.insert(winner, winner_rating.update(&[(loser_rating, 1.)]));
+    ratings.insert(loser, loser_rating.update(&[(winner_rating, 0.)]));
+}
+
+// Best ratings first
+pub fn leaderboard(
+    ratings: &HashMap<u128, Rating>,
+    offset: usize,
+    limit: usize,
+) -> Vec<shared::LeaderBoardEntry> {
+    let mut entries = ratings.iter().collect::<Vec<_>>();
+    // Ties by id, so pages don't shuffle between requests
+    entries.sort_by(|(a_id, a), (b_id, b)| b.rating.total_cmp(&a.rating).then(a_id.cmp(b_id)));
+
+    entries
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|(user_id, rating)| shared::LeaderBoardEntry {
+            user_id: *user_id,
+            rating: rating.rating.round() as u32,
+            deviation: rating.deviation.round() as u32,
+        })
+        .collect()
+}
@@ -0,0 +1,252 @@
+use shared::{RoomError, RoomInfo};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use tokio::sync::Mutex;
+
+pub const MIN_CAPACITY: usize = 2;
+pub const MAX_CAPACITY: usize = 16;
+pub const MAX_NAME_LEN: usize = 32;
+
+// Seconds between everyone being ready and the match starting
+pub const COUNTDOWN_SECONDS: u32 = 3;
+
+pub type RoomMap = Arc<Mutex<HashMap<String, Room>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    // Until everyone is ready
+    Waiting,
+    // Holds the match's number, so a countdown that got cancelled knows it's stale
+    Countdown(u64),
+    Playing,
+    // Until someone readies up again
+    Finished,
+}
+
+#[derive(Debug)]
+pub struct Room {
+    name: String,
+    capacity: usize,
+    // In join order
+    users: Vec<u128>,
+    phase: Phase,
+    ready: HashSet<u128>,
+    matches: u64,
+    // Users that were there when the current (or last) match started
+    players: Vec<u128>,
+    // In elimination order
+    eliminated: Vec<u128>,
+    // Ranked 1v1 from the queue, the result changes the players' ratings
+    ranked: bool,
+}
+
+impl Room {
+    // The capacity is clamped to something playable
+    pub fn new(name: String, capacity: usize) -> Result<Self, RoomError> {
+        if name.trim().is_empty() || name.len() > MAX_NAME_LEN {
+            return Err(RoomError::InvalidName);
+        }
+
+        Ok(Self {
+            name,
+            capacity: capacity.clamp(MIN_CAPACITY, MAX_CAPACITY),
+            users: Vec::new(),
+            phase: Phase::Waiting,
+            ready: HashSet::new(),
+            matches: 0,
+            players: Vec::new(),
+            eliminated: Vec::new(),
+            ranked: false,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    pub fn users(&self) -> &[u128] {
+        &self.users
+    }
+    pub fn is_full(&self) -> bool {
+        self.users.len() >= self.capacity
+    }
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+    pub fn is_ranked(&self) -> bool {
+        self.ranked
+    }
+    pub fn set_ranked(&mut self, ranked: bool) {
+        self.ranked = ranked;
+    }
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+    pub fn is_ready(&self, user_id: u128) -> bool {
+        self.ready.contains(&user_id)
+    }
+    // Players of the match that have not topped out yet
+    pub fn alive(&self) -> Vec<u128> {
+        self.players
+            .iter()
+            .copied()
+            .filter(|id| !self.eliminated.contains(id))
+            .collect()
+    }
+
+    pub fn join(&mut self, user_id: u128) -> Result<(), RoomError> {
+        if self.users.contains(&user_id) {
+            return Err(RoomError::AlreadyInRoom);
+        }
+        if self.is_full() {
+            return Err(RoomError::Full);
+        }
+
+        self.users.push(user_id);
+        // Not everyone is ready anymore
+        self.cancel_countdown();
+        Ok(())
+    }
+
+    // Returns false if the user was not in the room
+    // Players leaving a match have to be eliminated first
+    pub fn leave(&mut self, user_id: u128) -> bool {
+        let len = self.users.len();
+        self.users.retain(|id| *id != user_id);
+        self.ready.remove(&user_id);
+        self.cancel_countdown();
+        self.users.len() != len
+    }
+
+    pub fn set_ready(&mut self, user_id: u128, ready: bool) -> Result<(), RoomError> {
+        if !self.users.contains(&user_id) {
+            return Err(RoomError::NotInRoom);
+        }
+        if self.phase == Phase::Playing {
+            return Err(RoomError::MatchInProgress);
+        }
+
+        if ready {
+            self.ready.insert(user_id);
+        } else {
+            self.ready.remove(&user_id);
+            self.cancel_countdown();
+        }
+        if self.phase == Phase::Finished {
+            self.phase = Phase::Waiting;
+        }
+        Ok(())
+    }
+
+    // Starts the countdown if everyone is ready, returns the number of the match it's for
+    pub fn try_countdown(&mut self) -> Option<u64> {
+        if self.phase != Phase::Waiting
+            || self.users.len() < MIN_CAPACITY
+            || !self.users.iter().all(|id| self.ready.contains(id))
+        {
+            return None;
+        }
+
+        self.matches += 1;
+        self.phase = Phase::Countdown(self.matches);
+        Some(self.matches)
+    }
+
+    fn cancel_countdown(&mut self) {
+        if let Phase::Countdown(_) = self.phase {
+            self.phase = Phase::Waiting;
+        }
+    }
+
+    // Ends the countdown of match `number`, returns its players, None if it got cancelled
+    pub fn begin(&mut self, number: u64) -> Option<Vec<u128>> {
+        if self.phase != Phase::Countdown(number) {
+            return None;
+        }
+
+        self.phase = Phase::Playing;
+        self.players = self.users.clone();
+        self.eliminated.clear();
+        self.ready.clear();
+        Some(self.players.clone())
+    }
+
+    // Returns the place the user finished at, None if it's not playing
+    pub fn eliminate(&mut self, user_id: u128) -> Option<usize> {
+        if self.phase != Phase::Playing
+            || !self.players.contains(&user_id)
+            || self.eliminated.contains(&user_id)
+        {
+            return None;
+        }
+
+        self.eliminated.push(user_id);
+        Some(self.players.len() - self.eliminated.len() + 1)
+    }
+
+    // Ends the match once at most one player is left, returns the placements, winner first
+    pub fn finish(&mut self) -> Option<Vec<u128>> {
+        let alive = self.alive();
+        if self.phase != Phase::Playing || alive.len() > 1 {
+            return None;
+        }
+
+        self.phase = Phase::Finished;
+        Some(
+            alive
+                .into_iter()
+                .chain(self.eliminated.iter().rev().copied())
+                .collect(),
+        )
+    }
+
+    pub fn info(&self) -> RoomInfo {
+        RoomInfo {
+            name: self.name.clone(),
+            players: self.users.len(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+// The room the user is in, there is at most one
+pub fn find_room(rooms: &HashMap<String, Room>, user_id: u128) -> Option<&Room> {
+    rooms.values().find(|room| room.users.contains(&user_id))
+}
+
+pub fn find_room_mut(rooms: &mut HashMap<String, Room>, user_id: u128) -> Option<&mut Room> {
+    rooms
+        .values_mut()
+        .find(|room| room.users.contains(&user_id))
+}
+
+// Everyone in the user's room except the user, empty if it's not in one
+pub fn room_mates(rooms: &HashMap<String, Room>, user_id: u128) -> Vec<u128> {
+    find_room(rooms, user_id)
+        .map(|room| {
+            room.users
+                .iter()
+                .copied()
+                .filter(|id| *id != user_id)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Who the user's garbage can go to, only the other players still alive during a match
+pub fn opponents(rooms: &HashMap<String, Room>, user_id: u128) -> Vec<u128> {
+    match find_room(rooms, user_id) {
+        Some(room) if room.phase == Phase::Playing => {
+            let alive = room.alive();
+            if !alive.contains(&user_id) {
+                return Vec::new();
+            }
+            alive.into_iter().filter(|id| *id != user_id).collect()
+        }
+        _ => room_mates(rooms, user_id),
+    }
+}
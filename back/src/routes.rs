@@ -1,9 +1,12 @@
+#[path = "routes/api.rs"]
+mod api;
+pub use api::{leaderboard, login, logout, register, Bearer};
 #[path = "routes/bots.rs"]
 mod bot_routes;
 pub use bot_routes::{bot_admin, bot_env, bot_wordpress, bot_wp, bot_wp_admin};
 #[path = "routes/websocket.rs"]
 mod ws;
-pub use ws::{ws_broadcast, ws_join, UserMap};
+pub use ws::{ws_broadcast, ws_join, UserMap, MAX_DRIFT};
 
 // Here are routes that are managed by the front end router, so just serve the page and let it do it's things
 macro_rules! front_route {
@@ -18,6 +21,8 @@ macro_rules! front_route {
 }
 
 front_route!(home, "/home");
+front_route!(settings, "/settings");
+front_route!(leaderboard_page, "/leaderboard");
 front_route!(_404, "/404");
 
 #[rocket::get("/")]
@@ -117,7 +122,11 @@ static_dir_server!(
         "style.css",
         "theme.css",
         "not_found.css",
-        "header.css"
+        "header.css",
+        "board.css",
+        "settings.css",
+        "lobby.css",
+        "leaderboard.css"
     ]
 );
 static_dir_server!(
@@ -0,0 +1,169 @@
+use crate::{
+    rating::RatingMap,
+    response::Response,
+    storage::{self, Db, Mode},
+};
+use rocket::{
+    http::{ContentType, Status},
+    request::{FromRequest, Outcome},
+    serde::json::Json,
+    Request, State,
+};
+use shared::{
+    AccountError, Credentials, LeaderBoard, LeaderBoardMode, ScoreEntry, Session,
+    LEADERBOARD_PAGE_SIZE as PAGE_SIZE,
+};
+
+fn json_response(status: Status, content: String) -> Response {
+    Response::builder()
+        .with_status(status)
+        .with_content(content)
+        .with_content_type(ContentType::JSON)
+        .build()
+}
+
+fn account_response(result: Result<Session, AccountError>) -> Response {
+    match result {
+        Ok(session) => json_response(Status::Ok, serde_json::to_string(&session).unwrap()),
+        Err(e) => {
+            let status = match e {
+                AccountError::InvalidUsername | AccountError::InvalidPassword => Status::BadRequest,
+                AccountError::UsernameTaken => Status::Conflict,
+                AccountError::WrongCredentials => Status::Unauthorized,
+                AccountError::Internal => Status::InternalServerError,
+            };
+            json_response(status, serde_json::to_string(&e).unwrap())
+        }
+    }
+}
+
+// The player behind the `Authorization: Bearer <token>` header
+pub struct Bearer {
+    pub player_id: u128,
+    pub token: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Bearer {
+    type Error = AccountError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(token) = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "))
+        else {
+            return Outcome::Error((Status::Unauthorized, AccountError::WrongCredentials));
+        };
+        let Some(db) = request.rocket().state::<Db>() else {
+            return Outcome::Error((Status::InternalServerError, AccountError::Internal));
+        };
+
+        let owned = token.to_string();
+        match storage::run(db, move |storage| {
+            crate::account::authenticate(storage, &owned)
+        })
+        .await
+        {
+            Some(player_id) => Outcome::Success(Bearer {
+                player_id,
+                token: token.to_string(),
+            }),
+            None => Outcome::Error((Status::Unauthorized, AccountError::WrongCredentials)),
+        }
+    }
+}
+
+// Hashing takes a while on purpose, it runs off the async workers, and outside of the database's lock
+#[rocket::post("/api/register", data = "<credentials>")]
+pub async fn register(credentials: Json<Credentials>, db: &State<Db>) -> Response {
+    let db = db.inner().clone();
+    let result = tokio::task::spawn_blocking(move || crate::account::register(&db, &credentials))
+        .await
+        .unwrap_or(Err(AccountError::Internal));
+
+    account_response(result)
+}
+
+#[rocket::post("/api/login", data = "<credentials>")]
+pub async fn login(credentials: Json<Credentials>, db: &State<Db>) -> Response {
+    let db = db.inner().clone();
+    let result = tokio::task::spawn_blocking(move || crate::account::login(&db, &credentials))
+        .await
+        .unwrap_or(Err(AccountError::Internal));
+
+    account_response(result)
+}
+
+#[rocket::post("/api/logout")]
+pub async fn logout(bearer: Bearer, db: &State<Db>) -> Response {
+    match storage::run(db, move |storage| {
+        crate::account::logout(storage, &bearer.token)
+    })
+    .await
+    {
+        Ok(_) => Response::builder().with_status(Status::NoContent).build(),
+        Err(e) => json_response(
+            Status::InternalServerError,
+            serde_json::to_string(&e).unwrap(),
+        ),
+    }
+}
+
+// Pages start at 1, a missing page is the first one
+#[rocket::get("/api/leaderboard?<mode>&<page>")]
+pub async fn leaderboard(
+    mode: &str,
+    page: Option<usize>,
+    ratings: &State<RatingMap>,
+    db: &State<Db>,
+) -> Response {
+    let Ok(mode) = mode.parse::<LeaderBoardMode>() else {
+        return Response::builder().with_status(Status::BadRequest).build();
+    };
+    let offset = page.unwrap_or(1).saturating_sub(1) * PAGE_SIZE;
+
+    let board = match mode {
+        LeaderBoardMode::Ranked => LeaderBoard::Ratings(crate::rating::leaderboard(
+            &*ratings.lock().await,
+            offset,
+            PAGE_SIZE,
+        )),
+        LeaderBoardMode::Sprint | LeaderBoardMode::Marathon => {
+            let mode = if mode == LeaderBoardMode::Sprint {
+                Mode::Sprint
+            } else {
+                Mode::Marathon
+            };
+
+            match storage::run(db, move |storage| {
+                storage.high_scores(mode, PAGE_SIZE, offset)
+            })
+            .await
+            {
+                Ok(scores) => LeaderBoard::Scores(
+                    scores
+                        .into_iter()
+                        .map(|score| ScoreEntry {
+                            user_id: score.player_id,
+                            points: score.points,
+                            lines: score.lines,
+                            time_ms: score.time_ms,
+                        })
+                        .collect(),
+                ),
+                Err(e) => {
+                    error!("Failed to read the {} high scores: {e}", mode.as_str());
+                    return Response::builder()
+                        .with_status(Status::InternalServerError)
+                        .build();
+                }
+            }
+        }
+    };
+
+    Response::builder()
+        .with_content(serde_json::to_string(&board).unwrap())
+        .with_content_type(ContentType::JSON)
+        .build()
+}
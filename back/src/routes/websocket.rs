@@ -1,37 +1,243 @@
 use futures::{stream::SplitSink, SinkExt, StreamExt};
 use rocket::{get, State};
 use rocket_ws::{stream::DuplexStream, Message};
-use shared::{ClientMessage, ServerMessage};
-use std::{collections::HashMap, sync::Arc};
+use crate::{
+    queue::SharedQueue,
+    rating::{leaderboard, rate_match, RatingMap, LEADERBOARD_SIZE},
+    room::{find_room, find_room_mut, opponents, room_mates, Phase, Room, RoomMap, COUNTDOWN_SECONDS},
+    session::{new_resume_token, resume_token_user, History, RESUME_GRACE},
+    storage::{self, Db, HighScore, Mode, Replay},
+};
+use shared::{
+    ClientMessage, Codec, Envelope, Frame, Header, Identicon, PlayerInfo, RoomError, ServerMessage, SoloMode,
+    PROTOCOL_VERSION,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tokio::sync::Mutex;
-use serde_json;
 
-pub type UserMap = Arc<Mutex<HashMap<u128, Arc<Mutex<SplitSink<DuplexStream, Message>>>>>>;
+pub struct User {
+    // None while the user is disconnected, during the grace period
+    pub sink: Option<SplitSink<DuplexStream, Message>>,
+    // The server's own copy of the user's game, built from the seed it sent and the steps the client sends back
+    pub game: tetris::Game,
+    pub seed: u64,
+    // None for the games of a match, their scores don't go on the leaderboard
+    pub solo: Option<SoloMode>,
+    // Every step of the game so far, saved as a replay once it's over
+    pub steps: Vec<tetris::Step>,
+    // When the game started on the server's clock, the client's updates have to keep up with it
+    pub started: Instant,
+    // Sum of the client's updates until the game ended
+    pub played: Duration,
+    // Garbage sent to the user that its client has not applied yet, oldest first, as (lines, hole, sent at)
+    pub sent_garbage: VecDeque<(u32, u8, Instant)>,
+    // The client's game diverged from the simulation, nothing it sends is trusted anymore
+    pub desynced: bool,
+    // Connected with a session token, guests get a new id every time so they are never rated
+    pub logged_in: bool,
+    // Shown to the others instead of the id, set once the id is known
+    pub name: String,
+    pub identicon: Identicon,
+    pub resume_token: String,
+    // Replayed to a new socket resuming this user
+    pub history: History,
+    // Bumped every time a socket resumes the user, so an old grace period does not remove it
+    pub connection: u64,
+    // When the socket got lost, while the user is disconnected
+    pub lost_at: Option<Instant>,
+    // Time spent disconnected that the client has not caught up with yet, the steps it buffered arrive that late
+    pub offline: Duration,
+    // Picked by the client when it connects
+    pub codec: Codec,
+}
+
+impl User {
+    pub fn new(sink: SplitSink<DuplexStream, Message>, seed: u64, codec: Codec) -> Self {
+        Self {
+            sink: Some(sink),
+            // Until it asks for something else
+            game: tetris::Game::new(SoloMode::Marathon.config(), tetris::SevenBag::new(seed)),
+            seed,
+            solo: Some(SoloMode::Marathon),
+            steps: Vec::new(),
+            started: Instant::now(),
+            played: Duration::ZERO,
+            sent_garbage: VecDeque::new(),
+            desynced: false,
+            logged_in: false,
+            name: String::new(),
+            identicon: Identicon { hue: 0, cells: 0 },
+            resume_token: String::new(),
+            history: History::default(),
+            connection: 0,
+            lost_at: None,
+            offline: Duration::ZERO,
+            codec,
+        }
+    }
+
+    pub fn info(&self, user_id: u128) -> PlayerInfo {
+        PlayerInfo {
+            user_id,
+            name: self.name.clone(),
+            identicon: self.identicon,
+        }
+    }
+
+    // A new game, from the seed of a match or alone
+    pub fn restart(&mut self, seed: u64, solo: Option<SoloMode>) {
+        let config = solo.map(|mode| mode.config()).unwrap_or_default();
+        self.game = tetris::Game::new(config, tetris::SevenBag::new(seed));
+        self.seed = seed;
+        self.solo = solo;
+        self.steps.clear();
+        self.started = Instant::now();
+        self.played = Duration::ZERO;
+        self.offline = Duration::ZERO;
+        self.sent_garbage.clear();
+        self.desynced = false;
+    }
+
+    pub async fn send(&mut self, server_message: &ServerMessage) {
+        self.send_envelope(server_message, None).await;
+    }
+
+    // The answer to the user's message with this id
+    pub async fn respond(&mut self, server_message: &ServerMessage, request: u64) {
+        self.send_envelope(server_message, Some(request)).await;
+    }
+
+    // Every message of the session goes through here, it's kept for a resume even if the user is disconnected right now
+    async fn send_envelope(&mut self, server_message: &ServerMessage, reply_to: Option<u64>) {
+        let frame = Envelope::new(self.history.next_id(), reply_to, server_message).encode(self.codec);
+        self.history.push(frame.clone());
+        self.write(frame).await;
+    }
+
+    // Only about the current socket, it won't be replayed
+    pub async fn send_to_socket(&mut self, server_message: &ServerMessage, reply_to: Option<u64>) {
+        self.write(Envelope::new(0, reply_to, server_message).encode(self.codec)).await;
+    }
+
+    async fn write(&mut self, frame: Frame) {
+        let Some(sink) = self.sink.as_mut() else {
+            return;
+        };
+
+        let message = match frame {
+            Frame::Text(text) => Message::Text(text),
+            Frame::Binary(bytes) => Message::Binary(bytes),
+        };
+        if let Err(e) = sink.send(message).await {
+            debug!("Failed to send a message to a user: {e}");
+        }
+    }
+}
+
+pub type UserMap = Arc<Mutex<HashMap<u128, Arc<Mutex<User>>>>>;
 
 pub const MAX_RETRIES: u32 = 5;
+// How far the client's updates can be from the server's clock, it sends its steps late and in batches
+pub const MAX_DRIFT: Duration = Duration::from_secs(3);
 
-#[get("/ws")]
+// Logged in players give their session token, the others play as guests with a new id every time
+// Guests can pick a name, logged in players are shown with their username
+// Messages are JSON text unless the client asks for the binary codec
+#[get("/ws?<token>&<name>&<codec>")]
 pub async fn ws_join<'a>(
     socket: rocket_ws::WebSocket,
+    token: Option<&str>,
+    name: Option<&str>,
+    codec: Option<&str>,
     user_map: &'a State<UserMap>,
-) -> rocket_ws::Channel<'a> {
+    room_map: &'a State<RoomMap>,
+    queue: &'a State<SharedQueue>,
+    ratings: &'a State<RatingMap>,
+    db: &'a State<Db>,
+) -> Result<rocket_ws::Channel<'a>, rocket::http::Status> {
     debug!("New websocket connection request received.");
 
-    socket.channel(move |stream| {
+    let codec = match codec.map(str::parse::<Codec>) {
+        Some(Ok(codec)) => codec,
+        Some(Err(())) => return Err(rocket::http::Status::BadRequest),
+        None => Codec::default(),
+    };
+
+    let player_id = match token {
+        Some(token) => match storage::run(db, {
+            let token = token.to_string();
+            move |storage| crate::account::authenticate(storage, &token)
+        })
+        .await
+        {
+            Some(player_id) => Some(player_id),
+            None => return Err(rocket::http::Status::Unauthorized),
+        },
+        None => None,
+    };
+    // One socket per account
+    if let Some(player_id) = player_id {
+        let existing = user_map.lock().await.get(&player_id).cloned();
+        if let Some(existing) = existing {
+            if existing.lock().await.sink.is_some() {
+                return Err(rocket::http::Status::Conflict);
+            }
+            // Lost its connection, logging in again starts over instead of resuming
+            remove_user(player_id, user_map, room_map, queue, ratings, db).await;
+        }
+    }
+
+    // A refused guest name falls back to a generated one, the client is told why
+    let mut refused = None;
+    let name = match (player_id, name) {
+        (Some(player_id), _) => match storage::run(db, move |storage| storage.username(player_id)).await {
+            Ok(username) => username,
+            Err(e) => {
+                error!("Failed to get the username of player {player_id}: {e}");
+                None
+            }
+        },
+        (None, Some(name)) => match crate::profile::validate_name(name) {
+            Ok(name) => Some(name),
+            Err(e) => {
+                debug!("Refused guest name {name:?}: {e:?}");
+                refused = Some(e);
+                None
+            }
+        },
+        (None, None) => None,
+    };
+
+    Ok(socket.channel(move |stream| {
         Box::pin(async move {
             let (sender, mut receiever) = stream.split();
-            // This creates a reference counting heap pointer to the sender with a mutex on top to prevent data races
-            let arc_sender = Arc::new(Mutex::new(sender));
 
-            // Add the user to the map, and a pointer to its sender, so we can send them messages through this ws
+            // The game this user plays, the client gets the seed and the server simulates the same game
+            let seed = random::get_inc(u64::MIN, u64::MAX);
+            // This creates a reference counting heap pointer to the user with a mutex on top to prevent data races
+            let mut arc_user = Arc::new(Mutex::new(User::new(sender, seed, codec)));
+
+            // Add the user to the map, and a pointer to it, so we can send them messages through this ws
             // Retry 5 times before failing
             let mut tries: u32 = 0;
             // let mut user_map = user_map.clone();
-            let mut user_id = random::get_inc(u128::MIN, u128::MAX);
+            let mut user_id = player_id.unwrap_or_else(|| random::get_inc(u128::MIN, u128::MAX));
             while tries < MAX_RETRIES {
                 debug!("Retry number: {tries} for user id: {user_id}");
                 if let Ok(mut user_map_guard) = user_map.try_lock() {
-                    
+                    // The account connected again in the meantime
+                    if player_id.is_some() && user_map_guard.contains_key(&user_id) {
+                        return Err(rocket_ws::result::Error::Http({
+                            let mut resp = http::Response::new(None);
+                            *resp.status_mut() = http::StatusCode::CONFLICT;
+                            resp
+                        }));
+                    }
+
                     // Generate a random user ID until we find one that does not exist in the map
                     while user_map_guard.contains_key(&user_id) {
                         // If the user ID already exists, generate a new one
@@ -39,7 +245,7 @@ pub async fn ws_join<'a>(
                         user_id = random::get_inc(u128::MIN, u128::MAX);
                         debug!("New user ID generated: {user_id}");
                     }
-                    user_map_guard.insert(user_id, arc_sender.clone());
+                    user_map_guard.insert(user_id, arc_user.clone());
                     debug!("User map updated with user id: {user_id}");
                     break;
                 } else {
@@ -59,135 +265,865 @@ pub async fn ws_join<'a>(
             }
             debug!("New ws user: {user_id}");
 
+            {
+                let mut user = arc_user.lock().await;
+                user.send_to_socket(&ServerMessage::Hello { version: PROTOCOL_VERSION }, None).await;
+
+                user.logged_in = player_id.is_some();
+                user.name = name.unwrap_or_else(|| crate::profile::guest_name(user_id));
+                user.identicon = crate::profile::identicon(user_id);
+                let info = user.info(user_id);
+                user.send(&ServerMessage::Welcome(info)).await;
+                if let Some(e) = refused {
+                    user.send(&ServerMessage::NameRefused(e)).await;
+                }
+                user.resume_token = new_resume_token(user_id);
+                let token = user.resume_token.clone();
+                user.send(&ServerMessage::ResumeToken(token)).await;
+
+                let config = user.game.config().clone();
+                user.send(&ServerMessage::Start { seed, config }).await;
+            }
+
+            // Closed on purpose, the user is gone for good, otherwise it can still resume
+            let mut closed = false;
+
             // Here are the messages we receive from this user's websocket,
             while let Some(message) = receiever.next().await {
                 match message {
                     Ok(rocket_ws::Message::Close(_)) => {
                         debug!("Closing ws for user {user_id}");
-                        let _e = arc_sender.lock().await.close();
-                        user_map.lock().await.remove(&user_id).unwrap();
+                        if let Some(mut sink) = arc_user.lock().await.sink.take() {
+                            let _e = sink.close().await;
+                        }
+                        remove_user(user_id, user_map, room_map, queue, ratings, db).await;
+                        closed = true;
                         // Here the following receiever.next will give a None so the loop will stop
                     }
-                    Ok(Message::Text(text_message)) => {
-                        // Parse the text message as a ClientMessage
-                        let client_message: ClientMessage = match serde_json::from_str(&text_message) {
-                            Ok(msg) => msg,
+                    Ok(message @ (Message::Text(_) | Message::Binary(_))) => {
+                        let frame = match message {
+                            Message::Binary(bytes) => Frame::Binary(bytes),
+                            Message::Text(text) => Frame::Text(text),
+                            _ => continue,
+                        };
+                        // The version first, the payload of another version would not parse
+                        let header = match Header::decode(&frame) {
+                            Ok(header) => header,
                             Err(e) => {
                                 debug!("Failed to parse message from user {user_id}: {e}");
                                 continue; // Skip this message
                             }
                         };
-                        debug!("Received message from user {user_id}: {client_message:?}");
-                        match &client_message {
-                            ClientMessage::BoardUpdate(board_update) => {
-                                // Handle the board update message
-                                debug!("User {user_id} sent a board update: {board_update:?}");
-                                // Broadcast the message to all users except the source user
-                                broadcast_message(user_id, &client_message, user_map).await;
+                        let request = header.id;
+                        if header.version != PROTOCOL_VERSION {
+                            debug!("Ignored a message of version {} from user {user_id}", header.version);
+                            let response = ServerMessage::UnsupportedVersion { supported: PROTOCOL_VERSION };
+                            arc_user.lock().await.send_to_socket(&response, Some(request)).await;
+                            continue;
+                        }
+                        // Parse the frame as a ClientMessage, whatever codec the client used
+                        let envelope = match Envelope::<ClientMessage>::decode(&frame) {
+                            Ok(envelope) => envelope,
+                            Err(e) => {
+                                debug!("Failed to parse message from user {user_id}: {e}");
+                                continue; // Skip this message
                             }
-                            ClientMessage::LinesDestroyed(line_shapes) => {
-                                // Handle the chat message
-                                debug!("User {user_id} sent a lines destroyed message: {line_shapes:?}");
-                                // TODO: should we use only user_map (and thus if GameOver -> close socket) or use a separate
-                                // list of users still alive?
-                                // Select a random user to send the message to
-                                let map_lock = user_map.lock().await;
-                                let keys: Vec<u128> = map_lock.keys().cloned().collect();
-                                // TODO: should we hold the lock for the whole LinesDestroyed case duration?
-                                drop(map_lock); // Release the lock before using the keys.
-                                if keys.len() < 2 {
-                                    debug!("Not enough users to send lines destroyed message to. Skipping.");
-                                    continue; // Not enough users to send the message to
+                        };
+                        let client_message = envelope.payload;
+                        debug!("Received message {request} from user {user_id}: {client_message:?}");
+                        match client_message {
+                            ClientMessage::Steps { steps, checksum } => {
+                                let Some(events) = simulate(user_id, &arc_user, steps, checksum).await else {
+                                    // Its game can't be trusted anymore, it forfeits the match it's in, if any
+                                    eliminate(user_id, user_map, room_map, ratings, db).await;
+                                    continue;
+                                };
+
+                                // Only what the simulation computed goes to the other users
+                                // The board once per batch, however many pieces got locked in it
+                                if events.iter().any(|event| matches!(event, tetris::Event::Locked(_))) {
+                                    let board = arc_user.lock().await.game.board().clone();
+                                    let msg = ClientMessage::BoardUpdate(board);
+                                    broadcast_message(user_id, &msg, user_map, room_map).await;
                                 }
-                                let mut victim_id = user_id;
-                                while victim_id == user_id {
-                                    // Pick a random user ID from the keys
-                                    victim_id = *random::pick(&keys);
+                                for event in events {
+                                    match event {
+                                        tetris::Event::Locked(_) => (),
+                                        tetris::Event::LinesCleared(lines) => {
+                                            let msg = ClientMessage::LinesDestroyed(lines);
+                                            broadcast_message(user_id, &msg, user_map, room_map).await;
+                                        }
+                                        tetris::Event::Attack(lines) => {
+                                            send_attack(user_id, lines, user_map, room_map).await;
+                                        }
+                                        tetris::Event::GameOver => {
+                                            save_game(user_id, &arc_user, db).await;
+                                            broadcast_message(user_id, &ClientMessage::GameOver, user_map, room_map).await;
+                                            eliminate(user_id, user_map, room_map, ratings, db).await;
+                                        }
+                                        tetris::Event::Finished => save_game(user_id, &arc_user, db).await,
+                                    }
                                 }
-                                debug!("User {user_id} sent a lines destroyed message to user {victim_id}: {line_shapes:?}");
-                                send_lines_destroyed_message(victim_id, &client_message, user_map).await;
                             }
-                            // TODO: broadcast board update to everyone (except source) and broadcast lines destroyed only
-                            // to one random person.
-                            ClientMessage::GameOver => {
-                                // Handle the game over message
-                                debug!("User {user_id} sent a game over message.");
-                                // Broadcast the game over message to all users except the source user
-                                broadcast_message(user_id, &client_message, user_map).await;
+                            ClientMessage::CreateRoom { name, capacity } => {
+                                let name = name.trim().to_string();
+                                queue.lock().await.leave(user_id);
+                                create_room(user_id, request, name, capacity, user_map, room_map).await;
+                            }
+                            ClientMessage::JoinRoom(name) => {
+                                queue.lock().await.leave(user_id);
+                                join_room(user_id, request, name.trim().to_string(), user_map, room_map).await;
+                            }
+                            ClientMessage::LeaveRoom => {
+                                let response = match leave_room(user_id, user_map, room_map, ratings, db).await {
+                                    true => ServerMessage::RoomLeft,
+                                    false => ServerMessage::RoomError(RoomError::NotInRoom),
+                                };
+                                arc_user.lock().await.respond(&response, request).await;
+                            }
+                            ClientMessage::ListRooms => {
+                                let mut rooms = room_map
+                                    .lock()
+                                    .await
+                                    .values()
+                                    .map(Room::info)
+                                    .collect::<Vec<_>>();
+                                rooms.sort_by(|a, b| a.name.cmp(&b.name));
+                                arc_user.lock().await.respond(&ServerMessage::RoomList(rooms), request).await;
+                            }
+                            ClientMessage::Ready(ready) => {
+                                set_ready(user_id, request, ready, user_map, room_map).await;
+                            }
+                            ClientMessage::JoinQueue { players, ranked } => {
+                                join_queue(user_id, request, players, ranked, user_map, room_map, queue, ratings).await;
+                            }
+                            ClientMessage::LeaveQueue => {
+                                queue.lock().await.leave(user_id);
+                                arc_user.lock().await.respond(&ServerMessage::QueueLeft, request).await;
+                            }
+                            ClientMessage::PlaySolo(mode) => {
+                                if queue.lock().await.leave(user_id) {
+                                    arc_user.lock().await.send(&ServerMessage::QueueLeft).await;
+                                }
+                                play_solo(user_id, request, mode, &arc_user, room_map).await;
+                            }
+                            ClientMessage::Resume { token, last_id } => {
+                                match resume(user_id, request, &token, last_id, user_map, room_map, queue).await {
+                                    Some((resumed_id, resumed)) => {
+                                        debug!("User {user_id} resumed user {resumed_id}");
+                                        user_id = resumed_id;
+                                        arc_user = resumed;
+                                    }
+                                    None => {
+                                        let mut user = arc_user.lock().await;
+                                        user.send_to_socket(&ServerMessage::ResumeFailed, Some(request)).await;
+                                    }
+                                }
+                            }
+                            ClientMessage::BoardUpdate(_)
+                            | ClientMessage::LinesDestroyed(_)
+                            | ClientMessage::GameOver => {
+                                // The server computes these itself from the steps, clients can't send them
+                                debug!("Rejected {client_message:?} from user {user_id}, only steps are accepted.");
                             }
                         }
                     }
                     Ok(m) => {
                         debug!("Unhandled message type: {m:?}");
                     }
-                    Err(_) => {
-                        print!("Failed to read user {user_id}'s receiver. Terminating connection.");
-                        let _e = arc_sender.lock().await.close();
-                        user_map.lock().await.remove(&user_id);
-                        // Here the following receiever.next will give a None so the loop will stop
+                    Err(e) => {
+                        debug!("Failed to read user {user_id}'s receiver: {e}");
+                        break;
                     }
                 }
             }
+
+            if !closed {
+                // The sink is useless now, messages are only kept until the user resumes
+                let connection = {
+                    let mut user = arc_user.lock().await;
+                    user.sink = None;
+                    user.lost_at = Some(Instant::now());
+                    user.connection
+                };
+                debug!("User {user_id} lost its connection, it can resume for {RESUME_GRACE:?}");
+
+                tokio::spawn(expire(
+                    user_id,
+                    connection,
+                    user_map.inner().clone(),
+                    room_map.inner().clone(),
+                    queue.inner().clone(),
+                    ratings.inner().clone(),
+                    db.inner().clone(),
+                ));
+            }
             // If we get to this point, the websocket is closed
             Ok(())
         })
+    }))
+}
+
+// Replays the client's steps on the server's game, returns the events they caused
+// None if the user is (or just got) flagged as desynced
+pub async fn simulate(
+    user_id: u128,
+    user: &Arc<Mutex<User>>,
+    steps: Vec<tetris::Step>,
+    checksum: u64,
+) -> Option<Vec<tetris::Event>> {
+    let mut user = user.lock().await;
+
+    if user.desynced {
+        return None;
+    }
+
+    // The batch the game ends in is checked too, its updates count until the end
+    let playing = !user.game.is_over();
+    let mut desynced = false;
+    for step in steps {
+        match step {
+            // Clients only get to apply garbage the server sent them, in the order it was sent
+            tetris::Step::Garbage { lines, hole } => {
+                if user.sent_garbage.pop_front().map(|(lines, hole, _)| (lines, hole)) != Some((lines, hole)) {
+                    desynced = true;
+                    break;
+                }
+            }
+            tetris::Step::Update(delta) if !user.game.is_over() => user.played += delta,
+            _ => (),
+        }
+
+        user.game.step(step);
+    }
+    // Recorded again while replaying, kept for the replay
+    let replayed = user.game.drain_steps().collect::<Vec<_>>();
+    user.steps.extend(replayed);
+
+    // Garbage can't be held back to stay alive longer, the client applies it soon after getting it
+    if !user.game.is_over() {
+        if let Some((_, _, sent_at)) = user.sent_garbage.front() {
+            if sent_at.elapsed() > MAX_DRIFT + user.offline {
+                debug!("User {user_id} did not apply garbage sent {:?} ago", sent_at.elapsed());
+                desynced = true;
+            }
+        }
+    }
+
+    // Updates that don't add up to the time that passed would slow down, or freeze, gravity and the lock delay
+    if playing {
+        let elapsed = user.started.elapsed();
+        if elapsed.saturating_sub(user.played) <= MAX_DRIFT {
+            // Caught up with what it buffered while disconnected
+            user.offline = Duration::ZERO;
+        }
+        if user.played.abs_diff(elapsed) > MAX_DRIFT + user.offline {
+            debug!("User {user_id} played {:?} in {elapsed:?}", user.played);
+            desynced = true;
+        }
+    }
+
+    if desynced || user.game.board().checksum() != checksum {
+        warn!("User {user_id} diverged from the server's simulation, ignoring it from now on");
+        user.desynced = true;
+        user.send(&ServerMessage::Desync).await;
+        return None;
+    }
+
+    Some(user.game.drain_events().collect())
+}
+
+// A new game alone, matches are played in rooms
+pub async fn play_solo(user_id: u128, request: u64, mode: SoloMode, user: &Arc<Mutex<User>>, room_map: &RoomMap) {
+    if find_room(&*room_map.lock().await, user_id).is_some() {
+        user.lock().await.respond(&ServerMessage::RoomError(RoomError::AlreadyInRoom), request).await;
+        return;
+    }
+
+    let seed = random::get_inc(u64::MIN, u64::MAX);
+    let mut user = user.lock().await;
+    user.restart(seed, Some(mode));
+    let config = user.game.config().clone();
+    user.respond(&ServerMessage::Start { seed, config }, request).await;
+}
+
+// The user's solo game is over, keeps the replay of it, and its score if it counts
+// A sprint only counts once the lines are all cleared, and no game with garbage in it does
+pub async fn save_game(user_id: u128, user: &Arc<Mutex<User>>, db: &Db) {
+    let (score, replay) = {
+        let user = user.lock().await;
+        let Some(solo) = user.solo else {
+            return;
+        };
+
+        let garbage = user.steps.iter().any(|step| matches!(step, tetris::Step::Garbage { .. }));
+        let counts = match solo {
+            SoloMode::Sprint => user.game.state() == tetris::State::Finished,
+            SoloMode::Marathon => true,
+        };
+        let mode = match solo {
+            SoloMode::Sprint => Mode::Sprint,
+            SoloMode::Marathon => Mode::Marathon,
+        };
+
+        let score = HighScore {
+            player_id: user_id,
+            points: user.game.score().points(),
+            lines: user.game.score().lines(),
+            // The server's clock, the client's own is only trusted within MAX_DRIFT
+            time_ms: user.started.elapsed().saturating_sub(user.offline).as_millis() as u64,
+        };
+        let replay = Replay {
+            player_id: user_id,
+            seed: user.seed,
+            config: user.game.config().clone(),
+            steps: user.steps.clone(),
+        };
+        ((counts && !garbage).then_some((mode, score)), replay)
+    };
+
+    storage::run(db, move |storage| {
+        if let Some((mode, score)) = score {
+            if let Err(e) = storage.add_high_score(mode, &score) {
+                error!("Failed to save the score of user {user_id}: {e}");
+            }
+        }
+        if let Err(e) = storage.add_replay(&replay) {
+            error!("Failed to save the replay of user {user_id}: {e}");
+        }
     })
+    .await;
 }
 
-pub async fn send_lines_destroyed_message (victim_id: u128, client_message: &ClientMessage, user_map: &State<UserMap>) {
-    let server_message = ServerMessage::Broadcast { user_id: victim_id, msg: client_message.clone() };
+// Garbage goes to one random other user of the room, the hole is picked here so both sides agree on it
+pub async fn send_attack(attacker_id: u128, lines: u32, user_map: &UserMap, room_map: &RoomMap) {
+    let keys = opponents(&*room_map.lock().await, attacker_id);
 
-    let map_lock = user_map.lock().await;
+    if keys.is_empty() {
+        debug!("Not enough users to send the attack to. Skipping.");
+        return;
+    }
+
+    // Pick a random user ID from the keys
+    let victim_id = *random::pick(&keys);
+    let Some(victim) = user_map.lock().await.get(&victim_id).cloned() else {
+        return;
+    };
 
-    // Send the lines destroyed message to the victim user
-    debug!("Sending lines destroyed message to user {victim_id}: {:?}", server_message);
-    if let Some(ws) = map_lock.get(&victim_id) {
-        let content = serde_json::to_string(&server_message).unwrap();
-        let mut sink = ws.lock().await;
+    let hole = random::get_inc(0u8, 9u8);
+    debug!("User {attacker_id} sent an attack of {lines} lines to user {victim_id}");
 
-        sink.send(Message::Text(format!("Broadcast: {}", content)))
-            .await
-            .unwrap();
-    } else {
-        debug!("User {victim_id} not found in user map. Not sending message.");
+    let mut victim = victim.lock().await;
+    victim.sent_garbage.push_back((lines, hole, Instant::now()));
+    victim
+        .send(&ServerMessage::Garbage {
+            from: attacker_id,
+            lines,
+            hole,
+        })
+        .await;
+}
+
+// The answer to one of the user's messages
+pub async fn respond_to(user_id: u128, request: u64, server_message: &ServerMessage, user_map: &UserMap) {
+    let user = user_map.lock().await.get(&user_id).cloned();
+
+    if let Some(user) = user {
+        user.lock().await.respond(server_message, request).await;
     }
 }
-pub async fn broadcast_message(source_user_id: u128, client_message: &ClientMessage, user_map: &State<UserMap>) {
-    let server_message = ServerMessage::Broadcast { user_id: source_user_id, msg: client_message.clone() };
 
-    let map_lock = user_map.lock().await;
+pub async fn send_to(user_ids: &[u128], server_message: &ServerMessage, user_map: &UserMap) {
+    // Release the map before locking the users
+    let users = {
+        let map_lock = user_map.lock().await;
+        user_ids
+            .iter()
+            .filter_map(|id| map_lock.get(id).cloned())
+            .collect::<Vec<_>>()
+    };
+
+    for user in users {
+        user.lock().await.send(server_message).await;
+    }
+}
+
+// How the users are shown to the others, the ones that left in the meantime are skipped
+pub async fn player_infos(user_ids: &[u128], user_map: &UserMap) -> Vec<PlayerInfo> {
+    let users = {
+        let map_lock = user_map.lock().await;
+        user_ids
+            .iter()
+            .filter_map(|id| Some((*id, map_lock.get(id)?.clone())))
+            .collect::<Vec<_>>()
+    };
+
+    let mut infos = Vec::with_capacity(users.len());
+    for (user_id, user) in users {
+        infos.push(user.lock().await.info(user_id));
+    }
+    infos
+}
 
-    // Broadcast the board update to all users except the source user
-    debug!("Broadcasting board update from user {source_user_id}: {:?}", server_message);
-    for (_user_id, ws) in map_lock.iter() {
-        if source_user_id == *_user_id {
-            // Do not send the message to the source user
-            continue;
+// Only to the users in the same room as the source user
+pub async fn broadcast_message(
+    source_user_id: u128,
+    client_message: &ClientMessage,
+    user_map: &UserMap,
+    room_map: &RoomMap,
+) {
+    let server_message = ServerMessage::Broadcast { user_id: source_user_id, msg: Box::new(client_message.clone()) };
+    let room_mates = room_mates(&*room_map.lock().await, source_user_id);
+
+    debug!("Broadcasting from user {source_user_id} to its room: {:?}", server_message);
+    send_to(&room_mates, &server_message, user_map).await;
+}
+
+pub async fn create_room(
+    user_id: u128,
+    request: u64,
+    name: String,
+    capacity: usize,
+    user_map: &UserMap,
+    room_map: &RoomMap,
+) {
+    let result = 'create: {
+        let mut rooms = room_map.lock().await;
+
+        if find_room(&rooms, user_id).is_some() {
+            break 'create Err(RoomError::AlreadyInRoom);
         }
-        let content = serde_json::to_string(&server_message).unwrap();
-        let mut sink = ws.lock().await;
+        if rooms.contains_key(&name) {
+            break 'create Err(RoomError::AlreadyExists);
+        }
+
+        let mut room = match Room::new(name.clone(), capacity) {
+            Ok(room) => room,
+            Err(e) => break 'create Err(e),
+        };
+        if let Err(e) = room.join(user_id) {
+            break 'create Err(e);
+        }
+
+        let users = room.users().to_vec();
+        rooms.insert(name.clone(), room);
+        Ok(users)
+    };
 
-        sink.send(Message::Text(format!("Broadcast: {}", content)))
-            .await
-            .unwrap();
+    let response = match result {
+        Ok(users) => {
+            debug!("User {user_id} created room {name}");
+            ServerMessage::RoomJoined {
+                name,
+                users: player_infos(&users, user_map).await,
+            }
+        }
+        Err(e) => ServerMessage::RoomError(e),
+    };
+    respond_to(user_id, request, &response, user_map).await;
+}
+
+pub async fn join_room(user_id: u128, request: u64, name: String, user_map: &UserMap, room_map: &RoomMap) {
+    let result = 'join: {
+        let mut rooms = room_map.lock().await;
+
+        if find_room(&rooms, user_id).is_some() {
+            break 'join Err(RoomError::AlreadyInRoom);
+        }
+        let Some(room) = rooms.get_mut(&name) else {
+            break 'join Err(RoomError::NotFound);
+        };
+        if let Err(e) = room.join(user_id) {
+            break 'join Err(e);
+        }
+
+        Ok(room.users().to_vec())
+    };
+
+    match result {
+        Ok(users) => {
+            debug!("User {user_id} joined room {name}");
+            let others = users.iter().copied().filter(|id| *id != user_id).collect::<Vec<_>>();
+
+            let users = player_infos(&users, user_map).await;
+            let joined = users.iter().find(|info| info.user_id == user_id).cloned();
+
+            respond_to(user_id, request, &ServerMessage::RoomJoined { name, users }, user_map).await;
+            if let Some(joined) = joined {
+                send_to(&others, &ServerMessage::UserJoined(joined), user_map).await;
+            }
+        }
+        Err(e) => respond_to(user_id, request, &ServerMessage::RoomError(e), user_map).await,
+    }
+}
+
+// Out of the queue, its room and the map
+pub async fn remove_user(
+    user_id: u128,
+    user_map: &UserMap,
+    room_map: &RoomMap,
+    queue: &SharedQueue,
+    ratings: &RatingMap,
+    db: &Db,
+) {
+    queue.lock().await.leave(user_id);
+    leave_room(user_id, user_map, room_map, ratings, db).await;
+    user_map.lock().await.remove(&user_id);
+}
+
+// Removes the user once the grace period is over, unless a socket resumed it in the meantime
+async fn expire(
+    user_id: u128,
+    connection: u64,
+    user_map: UserMap,
+    room_map: RoomMap,
+    queue: SharedQueue,
+    ratings: RatingMap,
+    db: Db,
+) {
+    tokio::time::sleep(RESUME_GRACE).await;
+
+    // Checked and taken out of the map at once, a resume locks the map too
+    {
+        let mut users = user_map.lock().await;
+        let Some(user) = users.get(&user_id).cloned() else {
+            return;
+        };
+        let user = user.lock().await;
+        if user.sink.is_some() || user.connection != connection {
+            return;
+        }
+        users.remove(&user_id);
+    }
+
+    debug!("User {user_id} did not resume in time, removing it");
+    queue.lock().await.leave(user_id);
+    leave_room(user_id, &user_map, &room_map, &ratings, &db).await;
+}
+
+// Moves the socket of the new user to the disconnected one the token belongs to, and sends it what it missed
+// The new user is dropped, so it can't have done anything yet
+async fn resume(
+    user_id: u128,
+    request: u64,
+    token: &str,
+    last_id: u64,
+    user_map: &UserMap,
+    room_map: &RoomMap,
+    queue: &SharedQueue,
+) -> Option<(u128, Arc<Mutex<User>>)> {
+    let resumed_id = resume_token_user(token)?;
+    if resumed_id == user_id
+        || queue.lock().await.contains(user_id)
+        || find_room(&*room_map.lock().await, user_id).is_some()
+    {
+        return None;
+    }
+
+    let mut users = user_map.lock().await;
+    let resumed = users.get(&resumed_id)?.clone();
+    let new = users.get(&user_id)?.clone();
+
+    let mut resumed_user = resumed.lock().await;
+    if resumed_user.sink.is_some() || resumed_user.resume_token != token {
+        return None;
+    }
+    let missed = resumed_user.history.since(last_id)?;
+
+    // The missed messages are replayed as they were encoded
+    let mut new_user = new.lock().await;
+    if new_user.codec != resumed_user.codec {
+        return None;
+    }
+    resumed_user.sink = new_user.sink.take();
+    drop(new_user);
+    resumed_user.connection += 1;
+    if let Some(lost_at) = resumed_user.lost_at.take() {
+        resumed_user.offline += lost_at.elapsed();
+    }
+    users.remove(&user_id);
+    drop(users);
+
+    resumed_user.send_to_socket(&ServerMessage::Resumed, Some(request)).await;
+    for frame in missed {
+        resumed_user.write(frame).await;
+    }
+    drop(resumed_user);
+
+    Some((resumed_id, resumed))
+}
+
+// Empty rooms are removed, returns false if the user was not in a room
+// Leaving during a match counts as topping out
+pub async fn leave_room(
+    user_id: u128,
+    user_map: &UserMap,
+    room_map: &RoomMap,
+    ratings: &RatingMap,
+    db: &Db,
+) -> bool {
+    eliminate(user_id, user_map, room_map, ratings, db).await;
+
+    let others = {
+        let mut rooms = room_map.lock().await;
+
+        let Some(room) = find_room_mut(&mut rooms, user_id) else {
+            return false;
+        };
+        room.leave(user_id);
+        let name = room.name().to_string();
+
+        let others = room.users().to_vec();
+        if room.is_empty() {
+            debug!("Room {name} is empty, removing it");
+            rooms.remove(&name);
+        }
+        others
+    };
+
+    debug!("User {user_id} left its room");
+    send_to(&others, &ServerMessage::UserLeft(user_id), user_map).await;
+    true
+}
+
+pub async fn set_ready(user_id: u128, request: u64, ready: bool, user_map: &UserMap, room_map: &RoomMap) {
+    let result = {
+        let mut rooms = room_map.lock().await;
+
+        match find_room_mut(&mut rooms, user_id) {
+            Some(room) => room
+                .set_ready(user_id, ready)
+                .map(|()| (room.name().to_string(), room.users().to_vec(), room.try_countdown())),
+            None => Err(RoomError::NotInRoom),
+        }
+    };
+
+    match result {
+        Ok((name, users, countdown)) => {
+            send_to(&users, &ServerMessage::ReadyChanged { user_id, ready }, user_map).await;
+
+            if let Some(number) = countdown {
+                debug!("Everyone in room {name} is ready, starting match {number}");
+                tokio::spawn(countdown_match(name, number, user_map.clone(), room_map.clone()));
+            }
+        }
+        Err(e) => respond_to(user_id, request, &ServerMessage::RoomError(e), user_map).await,
+    }
+}
+
+// Runs on its own, stops if anyone leaves or un-readies before the end
+pub async fn countdown_match(name: String, number: u64, user_map: UserMap, room_map: RoomMap) {
+    for seconds in (1..=COUNTDOWN_SECONDS).rev() {
+        let users = {
+            let rooms = room_map.lock().await;
+            match rooms.get(&name) {
+                Some(room) if room.phase() == Phase::Countdown(number) => room.users().to_vec(),
+                _ => return,
+            }
+        };
+
+        send_to(&users, &ServerMessage::Countdown(seconds), &user_map).await;
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+
+    let Some(players) = room_map.lock().await.get_mut(&name).and_then(|room| room.begin(number)) else {
+        debug!("Match {number} of room {name} got cancelled");
+        return;
+    };
+
+    // Everyone plays the same pieces
+    let seed = random::get_inc(u64::MIN, u64::MAX);
+    let players = {
+        let map_lock = user_map.lock().await;
+        players.iter().filter_map(|id| map_lock.get(id).cloned()).collect::<Vec<_>>()
+    };
+
+    for player in players {
+        let mut player = player.lock().await;
+        player.restart(seed, None);
+        let config = player.game.config().clone();
+        player.send(&ServerMessage::Start { seed, config }).await;
+    }
+}
+
+// Tells the room the user topped out, and how the match ended if it did
+pub async fn eliminate(user_id: u128, user_map: &UserMap, room_map: &RoomMap, ratings: &RatingMap, db: &Db) {
+    let (users, place, placements, ranked) = {
+        let mut rooms = room_map.lock().await;
+
+        let Some(room) = find_room_mut(&mut rooms, user_id) else {
+            return;
+        };
+        let Some(place) = room.eliminate(user_id) else {
+            return;
+        };
+        (room.users().to_vec(), place, room.finish(), room.is_ranked())
+    };
+
+    debug!("User {user_id} got eliminated, place {place}");
+    send_to(&users, &ServerMessage::Eliminated { user_id, place }, user_map).await;
+
+    let Some(placements) = placements else {
+        return;
+    };
+    send_to(&users, &ServerMessage::MatchResult { placements: placements.clone() }, user_map).await;
+
+    let saved = placements.clone();
+    if let Err(e) = storage::run(db, move |storage| storage.add_match(ranked, &saved)).await {
+        error!("Failed to save the match of {placements:?}: {e}");
+    }
+
+    if let (true, [winner, loser]) = (ranked, placements.as_slice()) {
+        if !is_logged_in(*winner, user_map).await || !is_logged_in(*loser, user_map).await {
+            return;
+        }
+
+        let (entries, updated) = {
+            let mut ratings = ratings.lock().await;
+            rate_match(&mut ratings, *winner, *loser);
+            (leaderboard(&ratings, 0, LEADERBOARD_SIZE), [(*winner, ratings[winner]), (*loser, ratings[loser])])
+        };
+
+        storage::run(db, move |storage| {
+            for (player_id, rating) in updated {
+                if let Err(e) = storage.set_rating(player_id, &rating) {
+                    error!("Failed to save the rating of user {player_id}: {e}");
+                }
+            }
+        })
+        .await;
+
+        // Everyone connected gets it, not only the room
+        let everyone = user_map.lock().await.keys().copied().collect::<Vec<_>>();
+        send_to(&everyone, &ServerMessage::LeaderBoardUpdate(entries), user_map).await;
+    }
+}
+
+// Only logged in players get ranked matches and ratings
+async fn is_logged_in(user_id: u128, user_map: &UserMap) -> bool {
+    let user = user_map.lock().await.get(&user_id).cloned();
+
+    match user {
+        Some(user) => user.lock().await.logged_in,
+        None => false,
+    }
+}
+
+pub async fn join_queue(
+    user_id: u128,
+    request: u64,
+    players: usize,
+    ranked: bool,
+    user_map: &UserMap,
+    room_map: &RoomMap,
+    queue: &SharedQueue,
+    ratings: &RatingMap,
+) {
+    if find_room(&*room_map.lock().await, user_id).is_some() {
+        respond_to(user_id, request, &ServerMessage::RoomError(RoomError::AlreadyInRoom), user_map).await;
+        return;
+    }
+    if ranked && !is_logged_in(user_id, user_map).await {
+        respond_to(user_id, request, &ServerMessage::RoomError(RoomError::LoginRequired), user_map).await;
+        return;
+    }
+
+    let rating = ratings.lock().await.get(&user_id).copied().unwrap_or_default().rating;
+    let (players, wait) = {
+        let mut queue = queue.lock().await;
+        let now = std::time::Instant::now();
+
+        let players = if ranked {
+            queue.join_ranked(user_id, rating, now);
+            2
+        } else {
+            queue.join(user_id, players, now)
+        };
+        (players, queue.wait())
+    };
+    debug!("User {user_id} is looking for a match of {players} players");
+    respond_to(user_id, request, &ServerMessage::Queued { players }, user_map).await;
+
+    poll_queue(user_map, room_map, queue).await;
+
+    // Looked at again once the wait is over, in case nobody else shows up
+    let (user_map, room_map, queue) = (user_map.clone(), room_map.clone(), queue.clone());
+    tokio::spawn(async move {
+        tokio::time::sleep(wait).await;
+        poll_queue(&user_map, &room_map, &queue).await;
+    });
+}
+
+pub async fn poll_queue(user_map: &UserMap, room_map: &RoomMap, queue: &SharedQueue) {
+    let (groups, pairs) = {
+        let mut queue = queue.lock().await;
+        let now = std::time::Instant::now();
+        (queue.poll(now), queue.poll_ranked(now))
+    };
+
+    for group in groups {
+        start_quick_play(group, false, user_map, room_map, queue).await;
+    }
+    for pair in pairs {
+        start_quick_play(pair.to_vec(), true, user_map, room_map, queue).await;
+    }
+}
+
+// Puts the group in a new room with everyone ready, the usual countdown takes it from there
+async fn start_quick_play(group: Vec<u128>, ranked: bool, user_map: &UserMap, room_map: &RoomMap, queue: &SharedQueue) {
+    let result = 'start: {
+        let mut queue = queue.lock().await;
+        let mut rooms = room_map.lock().await;
+
+        // Joining a room leaves the queue, but it could have happened since the poll
+        let (group, gone): (Vec<u128>, Vec<u128>) = group.into_iter().partition(|id| find_room(&rooms, *id).is_none());
+        if group.len() < crate::room::MIN_CAPACITY {
+            break 'start Err(group.into_iter().chain(gone).collect::<Vec<_>>());
+        }
+
+        let mut name = queue.next_room_name();
+        while rooms.contains_key(&name) {
+            name = queue.next_room_name();
+        }
+
+        let mut room = Room::new(name.clone(), group.len()).unwrap();
+        room.set_ranked(ranked);
+        for id in group.iter() {
+            room.join(*id).unwrap();
+            room.set_ready(*id, true).unwrap();
+        }
+        let number = room.try_countdown().unwrap();
+        rooms.insert(name.clone(), room);
+
+        Ok((name, group, number))
+    };
+
+    match result {
+        Ok((name, users, number)) => {
+            debug!("Quick play match found in room {name} for {users:?}");
+            let infos = player_infos(&users, user_map).await;
+            send_to(&users, &ServerMessage::RoomJoined { name: name.clone(), users: infos }, user_map).await;
+
+            tokio::spawn(countdown_match(name, number, user_map.clone(), room_map.clone()));
+        }
+        // Out of the queue already, they have to queue again
+        Err(users) => send_to(&users, &ServerMessage::QueueLeft, user_map).await,
     }
 }
 
 #[rocket::get("/broadcast/<content>")]
 pub async fn ws_broadcast(content: &str, user_map: &State<UserMap>) -> crate::response::Response {
-    
+
     debug!("New broadcast request received.");
 
 
     let map_lock = user_map.lock().await;
 
     // Simple example on how to send a message to every saved websockets
-    for (_user_id, ws) in map_lock.iter() {
-        let mut sink = ws.lock().await;
+    for (_user_id, user) in map_lock.iter() {
+        let mut user = user.lock().await;
 
-        sink.send(Message::Text(format!("Broadcast: {}", content)))
-            .await
-            .unwrap();
+        user.send(&ServerMessage::Notice(content.to_string())).await;
     }
 
     crate::response::Response::builder()
@@ -0,0 +1,58 @@
+use shared::Frame;
+use std::{collections::VecDeque, time::Duration};
+
+// How long a user that lost its connection keeps its place, game and room included
+pub const RESUME_GRACE: Duration = Duration::from_secs(30);
+// Messages kept for a resume, a client that missed more has to start over
+pub const HISTORY_SIZE: usize = 256;
+
+// Starts with the user id, so the user is found without going through all of them
+pub fn new_resume_token(user_id: u128) -> String {
+    format!("{user_id:032x}{}", crate::account::new_token())
+}
+
+pub fn resume_token_user(token: &str) -> Option<u128> {
+    u128::from_str_radix(token.get(..32)?, 16).ok()
+}
+
+// The last messages sent to a user, as they were sent
+#[derive(Debug, Default)]
+pub struct History {
+    frames: VecDeque<Frame>,
+    // Every message ever sent, the kept ones are the last of them
+    sent: u64,
+}
+
+impl History {
+    pub fn sent(&self) -> u64 {
+        self.sent
+    }
+    // Messages are numbered from 1, in the order they are sent
+    pub fn next_id(&self) -> u64 {
+        self.sent + 1
+    }
+
+    pub fn push(&mut self, frame: Frame) {
+        self.sent += 1;
+        self.frames.push_back(frame);
+        if self.frames.len() > HISTORY_SIZE {
+            self.frames.pop_front();
+        }
+    }
+
+    // What was sent after the message with this id, None if some of it is not kept anymore
+    pub fn since(&self, last_id: u64) -> Option<Vec<Frame>> {
+        let first = self.sent - self.frames.len() as u64;
+        if last_id < first || last_id > self.sent {
+            return None;
+        }
+
+        Some(
+            self.frames
+                .iter()
+                .skip((last_id - first) as usize)
+                .cloned()
+                .collect(),
+        )
+    }
+}
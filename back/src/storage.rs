@@ -0,0 +1,440 @@
+use blake2::{Blake2s256, Digest as _};
+use rusqlite::{params, Connection, OptionalExtension as _};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::Mutex;
+
+// Path that opens a database living in memory only, for the tests
+pub const IN_MEMORY: &str = ":memory:";
+pub const DEFAULT_PATH: &str = "./data/tetris.db";
+
+// Applied in order at startup, the database's user_version is how many already are
+// Never edit one that shipped, add a new one instead
+const MIGRATIONS: &[&str] = &[
+    // Ids are u128s, stored as text since sqlite integers are 64 bits
+    "CREATE TABLE players (
+        id TEXT PRIMARY KEY,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE ratings (
+        player_id TEXT PRIMARY KEY REFERENCES players(id),
+        rating REAL NOT NULL,
+        deviation REAL NOT NULL,
+        volatility REAL NOT NULL
+    );
+    CREATE TABLE matches (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        ranked INTEGER NOT NULL,
+        finished_at INTEGER NOT NULL
+    );
+    CREATE TABLE match_players (
+        match_id INTEGER NOT NULL REFERENCES matches(id),
+        player_id TEXT NOT NULL REFERENCES players(id),
+        place INTEGER NOT NULL,
+        PRIMARY KEY (match_id, player_id)
+    );
+    CREATE TABLE high_scores (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        player_id TEXT NOT NULL REFERENCES players(id),
+        mode TEXT NOT NULL,
+        points INTEGER NOT NULL,
+        lines INTEGER NOT NULL,
+        time_ms INTEGER NOT NULL,
+        achieved_at INTEGER NOT NULL
+    );
+    CREATE INDEX high_scores_by_mode ON high_scores (mode, points DESC);
+    CREATE TABLE replays (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        player_id TEXT NOT NULL REFERENCES players(id),
+        seed TEXT NOT NULL,
+        config TEXT NOT NULL,
+        steps TEXT NOT NULL,
+        recorded_at INTEGER NOT NULL
+    );",
+    // Players that registered, the others are guests
+    "CREATE TABLE accounts (
+        player_id TEXT PRIMARY KEY REFERENCES players(id),
+        username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        password_hash TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE sessions (
+        token_hash TEXT PRIMARY KEY,
+        player_id TEXT NOT NULL REFERENCES players(id),
+        expires_at INTEGER NOT NULL
+    );",
+];
+
+pub type Db = Arc<Mutex<Storage>>;
+
+// Queries block, they run on a thread of their own instead of holding up the async workers
+pub async fn run<T: Send + 'static>(
+    db: &Db,
+    query: impl FnOnce(&mut Storage) -> T + Send + 'static,
+) -> T {
+    let db = db.clone();
+    match tokio::task::spawn_blocking(move || query(&mut db.blocking_lock())).await {
+        Ok(result) => result,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    // 40 lines as fast as possible
+    Sprint,
+    // As many points as possible before topping out
+    Marathon,
+}
+
+impl Mode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sprint => "sprint",
+            Self::Marathon => "marathon",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighScore {
+    pub player_id: u128,
+    pub points: u64,
+    pub lines: u32,
+    pub time_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    pub player_id: u128,
+    pub seed: u64,
+    pub config: tetris::Config,
+    pub steps: Vec<tetris::Step>,
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() as i64)
+}
+
+// Ids and seeds don't fit in sqlite's integers
+fn parse<T: std::str::FromStr>(text: String) -> rusqlite::Result<T> {
+    text.parse()
+        .map_err(|_| rusqlite::Error::InvalidColumnType(0, text, rusqlite::types::Type::Text))
+}
+
+// Configs and steps are stored as json
+fn to_sql_error(e: serde_json::Error) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(Box::new(e))
+}
+
+fn from_sql_error(e: serde_json::Error) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+}
+
+// Sessions only keep this, a leaked database does not log anyone in
+fn token_hash(token: &str) -> String {
+    Blake2s256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+pub struct Storage {
+    connection: Connection,
+}
+
+impl Storage {
+    // Creates the file if needed, and migrates it
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        if path == IN_MEMORY {
+            return Self::in_memory();
+        }
+
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        Self::new(Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> rusqlite::Result<Self> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(connection: Connection) -> rusqlite::Result<Self> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+
+        let mut storage = Self { connection };
+        storage.migrate()?;
+        Ok(storage)
+    }
+
+    // Number of migrations applied
+    pub fn version(&self) -> rusqlite::Result<usize> {
+        self.connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+    }
+
+    fn migrate(&mut self) -> rusqlite::Result<()> {
+        let version = self.version()?;
+
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            debug!("Applying database migration {}", i + 1);
+
+            // All or nothing, a failed migration does not leave half a schema behind
+            let transaction = self.connection.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", i + 1)?;
+            transaction.commit()?;
+        }
+
+        Ok(())
+    }
+
+    // Does nothing if the player already exists
+    pub fn add_player(&self, player_id: u128) -> rusqlite::Result<()> {
+        self.connection.execute(
+            "INSERT OR IGNORE INTO players (id, created_at) VALUES (?1, ?2)",
+            params![player_id.to_string(), now()],
+        )?;
+        Ok(())
+    }
+
+    pub fn rating(&self, player_id: u128) -> rusqlite::Result<Option<crate::rating::Rating>> {
+        self.connection
+            .query_row(
+                "SELECT rating, deviation, volatility FROM ratings WHERE player_id = ?1",
+                params![player_id.to_string()],
+                |row| {
+                    Ok(crate::rating::Rating::new(
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                    ))
+                },
+            )
+            .optional()
+    }
+
+    pub fn ratings(&self) -> rusqlite::Result<HashMap<u128, crate::rating::Rating>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT player_id, rating, deviation, volatility FROM ratings")?;
+
+        let rows = statement.query_map([], |row| {
+            Ok((
+                parse(row.get(0)?)?,
+                crate::rating::Rating::new(row.get(1)?, row.get(2)?, row.get(3)?),
+            ))
+        })?;
+        rows.collect()
+    }
+
+    pub fn set_rating(
+        &self,
+        player_id: u128,
+        rating: &crate::rating::Rating,
+    ) -> rusqlite::Result<()> {
+        self.add_player(player_id)?;
+        self.connection.execute(
+            "INSERT OR REPLACE INTO ratings (player_id, rating, deviation, volatility) VALUES (?1, ?2, ?3, ?4)",
+            params![player_id.to_string(), rating.rating, rating.deviation, rating.volatility],
+        )?;
+        Ok(())
+    }
+
+    // `placements` winner first, returns the id of the match
+    pub fn add_match(&mut self, ranked: bool, placements: &[u128]) -> rusqlite::Result<i64> {
+        let transaction = self.connection.transaction()?;
+
+        transaction.execute(
+            "INSERT INTO matches (ranked, finished_at) VALUES (?1, ?2)",
+            params![ranked, now()],
+        )?;
+        let match_id = transaction.last_insert_rowid();
+
+        for (i, player_id) in placements.iter().enumerate() {
+            transaction.execute(
+                "INSERT OR IGNORE INTO players (id, created_at) VALUES (?1, ?2)",
+                params![player_id.to_string(), now()],
+            )?;
+            transaction.execute(
+                "INSERT INTO match_players (match_id, player_id, place) VALUES (?1, ?2, ?3)",
+                params![match_id, player_id.to_string(), i + 1],
+            )?;
+        }
+
+        transaction.commit()?;
+        Ok(match_id)
+    }
+
+    // Players of the match, winner first
+    pub fn placements(&self, match_id: i64) -> rusqlite::Result<Vec<u128>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT player_id FROM match_players WHERE match_id = ?1 ORDER BY place")?;
+
+        let rows = statement.query_map(params![match_id], |row| parse(row.get(0)?))?;
+        rows.collect()
+    }
+
+    pub fn add_high_score(&self, mode: Mode, score: &HighScore) -> rusqlite::Result<()> {
+        self.add_player(score.player_id)?;
+        self.connection.execute(
+            "INSERT INTO high_scores (player_id, mode, points, lines, time_ms, achieved_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                score.player_id.to_string(),
+                mode.as_str(),
+                score.points as i64,
+                score.lines,
+                score.time_ms as i64,
+                now()
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Sprints are ranked by time, the rest by points
+    pub fn high_scores(
+        &self,
+        mode: Mode,
+        limit: usize,
+        offset: usize,
+    ) -> rusqlite::Result<Vec<HighScore>> {
+        let order = match mode {
+            Mode::Sprint => "time_ms ASC",
+            Mode::Marathon => "points DESC",
+        };
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT player_id, points, lines, time_ms FROM high_scores WHERE mode = ?1 ORDER BY {order}, id LIMIT ?2 OFFSET ?3"
+        ))?;
+
+        let rows =
+            statement.query_map(params![mode.as_str(), limit as i64, offset as i64], |row| {
+                Ok(HighScore {
+                    player_id: parse(row.get(0)?)?,
+                    points: row.get::<_, i64>(1)? as u64,
+                    lines: row.get(2)?,
+                    time_ms: row.get::<_, i64>(3)? as u64,
+                })
+            })?;
+        rows.collect()
+    }
+
+    // Returns the id of the replay
+    pub fn add_replay(&self, replay: &Replay) -> rusqlite::Result<i64> {
+        self.add_player(replay.player_id)?;
+        self.connection.execute(
+            "INSERT INTO replays (player_id, seed, config, steps, recorded_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                replay.player_id.to_string(),
+                replay.seed.to_string(),
+                serde_json::to_string(&replay.config).map_err(to_sql_error)?,
+                serde_json::to_string(&replay.steps).map_err(to_sql_error)?,
+                now()
+            ],
+        )?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    // Returns false if the username is taken, case doesn't matter
+    pub fn add_account(
+        &self,
+        player_id: u128,
+        username: &str,
+        password_hash: &str,
+    ) -> rusqlite::Result<bool> {
+        self.add_player(player_id)?;
+        let added = self.connection.execute(
+            "INSERT OR IGNORE INTO accounts (player_id, username, password_hash, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![player_id.to_string(), username, password_hash, now()],
+        )?;
+        Ok(added == 1)
+    }
+
+    // The player id and password hash of the account
+    pub fn account(&self, username: &str) -> rusqlite::Result<Option<(u128, String)>> {
+        self.connection
+            .query_row(
+                "SELECT player_id, password_hash FROM accounts WHERE username = ?1",
+                params![username],
+                |row| Ok((parse(row.get(0)?)?, row.get(1)?)),
+            )
+            .optional()
+    }
+
+    // As it was registered, the lookup ignores the case
+    pub fn username(&self, player_id: u128) -> rusqlite::Result<Option<String>> {
+        self.connection
+            .query_row(
+                "SELECT username FROM accounts WHERE player_id = ?1",
+                params![player_id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    // `expires_at` in seconds since the epoch
+    pub fn add_session(
+        &self,
+        token: &str,
+        player_id: u128,
+        expires_at: i64,
+    ) -> rusqlite::Result<()> {
+        self.connection.execute(
+            "INSERT INTO sessions (token_hash, player_id, expires_at) VALUES (?1, ?2, ?3)",
+            params![token_hash(token), player_id.to_string(), expires_at],
+        )?;
+        Ok(())
+    }
+
+    // The player of the session, None if it does not exist or expired
+    pub fn session(&self, token: &str) -> rusqlite::Result<Option<u128>> {
+        self.connection
+            .query_row(
+                "SELECT player_id FROM sessions WHERE token_hash = ?1 AND expires_at > ?2",
+                params![token_hash(token), now()],
+                |row| parse(row.get(0)?),
+            )
+            .optional()
+    }
+
+    // Returns false if there was no such session
+    pub fn remove_session(&self, token: &str) -> rusqlite::Result<bool> {
+        let removed = self.connection.execute(
+            "DELETE FROM sessions WHERE token_hash = ?1",
+            params![token_hash(token)],
+        )?;
+        Ok(removed == 1)
+    }
+
+    pub fn remove_expired_sessions(&self) -> rusqlite::Result<usize> {
+        self.connection.execute(
+            "DELETE FROM sessions WHERE expires_at <= ?1",
+            params![now()],
+        )
+    }
+
+    pub fn replay(&self, replay_id: i64) -> rusqlite::Result<Option<Replay>> {
+        self.connection
+            .query_row(
+                "SELECT player_id, seed, config, steps FROM replays WHERE id = ?1",
+                params![replay_id],
+                |row| {
+                    Ok(Replay {
+                        player_id: parse(row.get(0)?)?,
+                        seed: parse(row.get(1)?)?,
+                        config: serde_json::from_str(&row.get::<_, String>(2)?)
+                            .map_err(from_sql_error)?,
+                        steps: serde_json::from_str(&row.get::<_, String>(3)?)
+                            .map_err(from_sql_error)?,
+                    })
+                },
+            )
+            .optional()
+    }
+}
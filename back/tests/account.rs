@@ -0,0 +1,84 @@
+use back::{
+    account,
+    storage::{Db, Storage},
+};
+use shared::{AccountError, Credentials};
+
+fn credentials(username: &str, password: &str) -> Credentials {
+    Credentials {
+        username: username.to_string(),
+        password: password.to_string(),
+    }
+}
+
+#[test]
+fn validation() {
+    assert_eq!(account::validate(&credentials("bob", "hunter22")), Ok(()));
+    assert_eq!(
+        account::validate(&credentials("b", "hunter22")),
+        Err(AccountError::InvalidUsername)
+    );
+    assert_eq!(
+        account::validate(&credentials("bob smith", "hunter22")),
+        Err(AccountError::InvalidUsername)
+    );
+    assert_eq!(
+        account::validate(&credentials("bob", "short")),
+        Err(AccountError::InvalidPassword)
+    );
+}
+
+#[test]
+fn register_and_login() {
+    let db = Db::new(tokio::sync::Mutex::new(Storage::in_memory().unwrap()));
+
+    let session = account::register(&db, &credentials("Bob", "hunter22")).unwrap();
+    assert_eq!(
+        account::register(&db, &credentials("bob", "whatever1")),
+        Err(AccountError::UsernameTaken)
+    );
+
+    assert_eq!(
+        account::login(&db, &credentials("bob", "hunter23")),
+        Err(AccountError::WrongCredentials)
+    );
+    assert_eq!(
+        account::login(&db, &credentials("alice", "hunter22")),
+        Err(AccountError::WrongCredentials)
+    );
+
+    // Each login is its own session
+    let other = account::login(&db, &credentials("bob", "hunter22")).unwrap();
+    assert_eq!(other.player_id, session.player_id);
+    assert_ne!(other.token, session.token);
+
+    let storage = db.blocking_lock();
+    assert_eq!(
+        account::authenticate(&storage, &session.token),
+        Some(session.player_id)
+    );
+    assert_eq!(
+        storage.username(session.player_id).unwrap().as_deref(),
+        Some("Bob")
+    );
+
+    assert_eq!(account::logout(&storage, &session.token), Ok(true));
+    assert_eq!(account::logout(&storage, &session.token), Ok(false));
+    assert_eq!(account::authenticate(&storage, &session.token), None);
+    assert_eq!(
+        account::authenticate(&storage, &other.token),
+        Some(other.player_id)
+    );
+}
+
+#[test]
+fn expired_sessions() {
+    let storage = Storage::in_memory().unwrap();
+    storage.add_player(1).unwrap();
+    storage
+        .add_session("old", 1, back::storage::now() - 1)
+        .unwrap();
+
+    assert_eq!(account::authenticate(&storage, "old"), None);
+    assert_eq!(storage.remove_expired_sessions().unwrap(), 1);
+}
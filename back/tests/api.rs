@@ -0,0 +1,88 @@
+use rocket::{
+    http::{ContentType, Header, Status},
+    local::asynchronous::Client,
+};
+use shared::{AccountError, Credentials, LeaderBoard, Session};
+
+async fn client() -> Client {
+    // Nothing the tests do should end up on disk
+    std::env::set_var("ROCKET_DATABASE", back::storage::IN_MEMORY);
+
+    Client::untracked(back::build_rocket().await)
+        .await
+        .expect("valid rocket instance")
+}
+
+#[rocket::async_test]
+async fn leaderboard() {
+    let client = client().await;
+
+    let response = client.get("/api/leaderboard?mode=ranked").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let board =
+        serde_json::from_str::<LeaderBoard>(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(board, LeaderBoard::Ratings(Vec::new()));
+
+    let response = client
+        .get("/api/leaderboard?mode=sprint&page=2")
+        .dispatch()
+        .await;
+    let board =
+        serde_json::from_str::<LeaderBoard>(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(board, LeaderBoard::Scores(Vec::new()));
+
+    let response = client.get("/api/leaderboard?mode=blitz").dispatch().await;
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[rocket::async_test]
+async fn accounts() {
+    let client = client().await;
+    let credentials = |password: &str| {
+        serde_json::to_string(&Credentials {
+            username: String::from("bob"),
+            password: password.to_string(),
+        })
+        .unwrap()
+    };
+
+    let response = client
+        .post("/api/register")
+        .header(ContentType::JSON)
+        .body(credentials("hunter22"))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let session = serde_json::from_str::<Session>(&response.into_string().await.unwrap()).unwrap();
+
+    let response = client
+        .post("/api/register")
+        .header(ContentType::JSON)
+        .body(credentials("hunter22"))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Conflict);
+
+    let response = client
+        .post("/api/login")
+        .header(ContentType::JSON)
+        .body(credentials("wrong password"))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+    let error =
+        serde_json::from_str::<AccountError>(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(error, AccountError::WrongCredentials);
+
+    let logout = || {
+        client
+            .post("/api/logout")
+            .header(Header::new(
+                "Authorization",
+                format!("Bearer {}", session.token),
+            ))
+            .dispatch()
+    };
+    assert_eq!(logout().await.status(), Status::NoContent);
+    assert_eq!(logout().await.status(), Status::Unauthorized);
+}
@@ -0,0 +1,53 @@
+use back::profile::{guest_name, identicon, validate_name, NameError};
+use shared::Identicon;
+
+#[test]
+fn names() {
+    assert_eq!(
+        validate_name("  Bob   the  Builder "),
+        Ok("Bob the Builder".to_string())
+    );
+    assert_eq!(validate_name("x_x-99"), Ok("x_x-99".to_string()));
+
+    assert_eq!(validate_name("ab"), Err(NameError::Length));
+    assert_eq!(validate_name("   "), Err(NameError::Length));
+    assert_eq!(validate_name("a name way too long"), Err(NameError::Length));
+    assert_eq!(validate_name("<script>"), Err(NameError::Characters));
+    assert_eq!(validate_name("Zoë"), Err(NameError::Characters));
+}
+
+#[test]
+fn profanity() {
+    assert_eq!(validate_name("fuck"), Err(NameError::Profanity));
+    assert_eq!(validate_name("xX_ShIt_Xx"), Err(NameError::Profanity));
+    assert_eq!(validate_name("f_u c-k"), Err(NameError::Profanity));
+    assert_eq!(validate_name("5h1t"), Err(NameError::Profanity));
+
+    assert_eq!(
+        validate_name("Classic Hello"),
+        Ok("Classic Hello".to_string())
+    );
+}
+
+#[test]
+fn guests() {
+    assert_eq!(guest_name(42), "Guest-0042");
+    assert_eq!(guest_name(123_456), "Guest-3456");
+}
+
+#[test]
+fn identicons() {
+    assert_eq!(identicon(7), identicon(7));
+    assert_ne!(identicon(7), identicon(8));
+
+    let icon = identicon(u128::MAX);
+    assert!(icon.hue < 360);
+    for y in 0..Identicon::SIZE {
+        for x in 0..Identicon::SIZE {
+            assert_eq!(
+                icon.is_filled(x, y),
+                icon.is_filled(Identicon::SIZE - 1 - x, y)
+            );
+        }
+    }
+}
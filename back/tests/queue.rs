@@ -0,0 +1,107 @@
+use back::queue::Queue;
+use std::time::{Duration, Instant};
+
+#[test]
+fn full_matches() {
+    let mut queue = Queue::new(Duration::from_secs(30));
+    let now = Instant::now();
+
+    assert_eq!(queue.join(1, 2, now), 2);
+    assert_eq!(queue.join(2, 3, now), 3);
+    assert!(queue.poll(now).is_empty());
+
+    queue.join(3, 2, now);
+    queue.join(4, 3, now);
+    queue.join(5, 2, now);
+    assert_eq!(queue.poll(now), vec![vec![1, 3]]);
+    assert_eq!(queue.len(), 3);
+
+    queue.join(6, 3, now);
+    assert_eq!(queue.poll(now), vec![vec![2, 4, 6]]);
+    assert!(queue.contains(5));
+}
+
+#[test]
+fn wait() {
+    let mut queue = Queue::new(Duration::from_secs(30));
+    let now = Instant::now();
+
+    queue.join(1, 4, now);
+    queue.join(2, 4, now + Duration::from_secs(10));
+    assert!(queue.poll(now + Duration::from_secs(29)).is_empty());
+
+    // The oldest waited long enough, the match starts with the two of them
+    assert_eq!(queue.poll(now + Duration::from_secs(30)), vec![vec![1, 2]]);
+
+    // Nobody to play against, however long it waits
+    queue.join(3, 4, now);
+    assert!(queue.poll(now + Duration::from_secs(60)).is_empty());
+}
+
+#[test]
+fn leave_and_requeue() {
+    let mut queue = Queue::new(Duration::from_secs(30));
+    let now = Instant::now();
+
+    queue.join(1, 2, now);
+    assert!(queue.leave(1));
+    assert!(!queue.leave(1));
+    assert!(queue.is_empty());
+
+    // Queueing again replaces the ticket
+    queue.join(2, 2, now);
+    queue.join(2, 3, now);
+    assert_eq!(queue.len(), 1);
+    queue.join(3, 2, now);
+    assert!(queue.poll(now).is_empty());
+
+    // Sizes are clamped like room capacities
+    assert_eq!(queue.join(4, 0, now), 2);
+    assert_eq!(queue.poll(now), vec![vec![3, 4]]);
+}
+
+#[test]
+fn ranked() {
+    let mut queue = Queue::new(Duration::from_secs(30));
+    let now = Instant::now();
+
+    queue.join_ranked(1, 1500., now);
+    queue.join_ranked(2, 2000., now);
+    // Casual players never get paired with ranked ones
+    queue.join(3, 2, now);
+    assert!(queue.poll_ranked(now).is_empty());
+    assert!(queue.poll(now).is_empty());
+
+    // The closest rating wins
+    queue.join_ranked(4, 1580., now);
+    queue.join_ranked(5, 1450., now);
+    assert_eq!(queue.poll_ranked(now), vec![[1, 5]]);
+
+    // The window grows with the wait, until anyone goes
+    assert!(queue.poll_ranked(now + Duration::from_secs(10)).is_empty());
+    assert_eq!(
+        queue.poll_ranked(now + Duration::from_secs(30)),
+        vec![[2, 4]]
+    );
+    assert!(queue.contains(3));
+}
+
+#[test]
+fn ranked_window_of_both() {
+    let mut queue = Queue::new(Duration::from_secs(30));
+    let now = Instant::now();
+    let later = now + Duration::from_secs(30);
+
+    queue.join_ranked(1, 1500., now);
+    // Player 1 accepts anyone by now, the newcomer does not
+    queue.join_ranked(2, 2000., later);
+    assert!(queue.poll_ranked(later).is_empty());
+    assert!(queue
+        .poll_ranked(later + Duration::from_secs(10))
+        .is_empty());
+
+    assert_eq!(
+        queue.poll_ranked(later + Duration::from_secs(30)),
+        vec![[1, 2]]
+    );
+}
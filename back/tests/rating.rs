@@ -0,0 +1,49 @@
+use back::rating::{leaderboard, rate_match, Rating};
+use std::collections::HashMap;
+
+#[test]
+fn glicko2_paper() {
+    // The example from the paper
+    let player = Rating::new(1500., 200., 0.06);
+    let results = [
+        (Rating::new(1400., 30., 0.06), 1.),
+        (Rating::new(1550., 100., 0.06), 0.),
+        (Rating::new(1700., 300., 0.06), 0.),
+    ];
+
+    let updated = player.update(&results);
+    assert!((updated.rating - 1464.06).abs() < 0.01, "{updated:?}");
+    assert!((updated.deviation - 151.52).abs() < 0.01, "{updated:?}");
+    assert!(
+        (updated.volatility - 0.05999).abs() < 0.00001,
+        "{updated:?}"
+    );
+}
+
+#[test]
+fn inactive() {
+    let player = Rating::new(1500., 200., 0.06);
+    let updated = player.update(&[]);
+
+    assert_eq!(updated.rating, 1500.);
+    assert!(updated.deviation > 200.);
+}
+
+#[test]
+fn ranked_match() {
+    let mut ratings = HashMap::new();
+    rate_match(&mut ratings, 1, 2);
+
+    assert!(ratings[&1].rating > 1500.);
+    assert!(ratings[&2].rating < 1500.);
+    assert!(ratings[&1].deviation < 350.);
+
+    rate_match(&mut ratings, 3, 2);
+    let board = leaderboard(&ratings, 0, 10);
+    assert_eq!(board.len(), 3);
+    assert_eq!(board[2].user_id, 2);
+    assert!(board[0].rating >= board[1].rating);
+
+    assert_eq!(leaderboard(&ratings, 2, 10), board[2..]);
+    assert_eq!(leaderboard(&ratings, 1, 1), board[1..2]);
+}
@@ -0,0 +1,134 @@
+use back::room::{find_room, opponents, room_mates, Phase, Room, MAX_CAPACITY, MIN_CAPACITY};
+use shared::RoomError;
+use std::collections::HashMap;
+
+#[test]
+fn new_room() {
+    assert_eq!(
+        Room::new(String::from("  "), 4).unwrap_err(),
+        RoomError::InvalidName
+    );
+    assert_eq!(
+        Room::new("a".repeat(33), 4).unwrap_err(),
+        RoomError::InvalidName
+    );
+
+    assert_eq!(
+        Room::new(String::from("room"), 0).unwrap().capacity(),
+        MIN_CAPACITY
+    );
+    assert_eq!(
+        Room::new(String::from("room"), 100).unwrap().capacity(),
+        MAX_CAPACITY
+    );
+}
+
+#[test]
+fn join_and_leave() {
+    let mut room = Room::new(String::from("room"), 2).unwrap();
+
+    assert_eq!(room.join(1), Ok(()));
+    assert_eq!(room.join(1), Err(RoomError::AlreadyInRoom));
+    assert_eq!(room.join(2), Ok(()));
+    assert!(room.is_full());
+    assert_eq!(room.join(3), Err(RoomError::Full));
+
+    assert!(room.leave(1));
+    assert!(!room.leave(1));
+    assert_eq!(room.users(), &[2]);
+    assert_eq!(room.info().players, 1);
+}
+
+#[test]
+fn mates() {
+    let mut rooms = HashMap::new();
+    let mut a = Room::new(String::from("a"), 4).unwrap();
+    let mut b = Room::new(String::from("b"), 4).unwrap();
+    a.join(1).unwrap();
+    a.join(2).unwrap();
+    b.join(3).unwrap();
+    rooms.insert(String::from("a"), a);
+    rooms.insert(String::from("b"), b);
+
+    assert_eq!(find_room(&rooms, 3).unwrap().name(), "b");
+    assert_eq!(room_mates(&rooms, 1), vec![2]);
+    assert!(room_mates(&rooms, 3).is_empty());
+    assert!(room_mates(&rooms, 4).is_empty());
+}
+
+#[test]
+fn ready_up() {
+    let mut room = Room::new(String::from("room"), 4).unwrap();
+    room.join(1).unwrap();
+
+    // Alone it never starts
+    room.set_ready(1, true).unwrap();
+    assert_eq!(room.try_countdown(), None);
+
+    room.join(2).unwrap();
+    assert_eq!(room.set_ready(3, true), Err(RoomError::NotInRoom));
+    room.set_ready(2, true).unwrap();
+    assert_eq!(room.try_countdown(), Some(1));
+    assert_eq!(room.phase(), Phase::Countdown(1));
+
+    // Un-readying cancels it, and the stale countdown can't start the match anymore
+    room.set_ready(2, false).unwrap();
+    assert_eq!(room.phase(), Phase::Waiting);
+    room.set_ready(2, true).unwrap();
+    assert_eq!(room.try_countdown(), Some(2));
+    assert_eq!(room.begin(1), None);
+
+    assert_eq!(room.begin(2), Some(vec![1, 2]));
+    assert_eq!(room.phase(), Phase::Playing);
+    assert!(!room.is_ready(1));
+    assert_eq!(room.set_ready(1, true), Err(RoomError::MatchInProgress));
+}
+
+#[test]
+fn joining_cancels_countdown() {
+    let mut room = Room::new(String::from("room"), 4).unwrap();
+    room.join(1).unwrap();
+    room.join(2).unwrap();
+    room.set_ready(1, true).unwrap();
+    room.set_ready(2, true).unwrap();
+    room.try_countdown().unwrap();
+
+    room.join(3).unwrap();
+    assert_eq!(room.phase(), Phase::Waiting);
+    assert_eq!(room.try_countdown(), None);
+}
+
+#[test]
+fn placements() {
+    let mut rooms = HashMap::new();
+    let mut room = Room::new(String::from("room"), 4).unwrap();
+    for id in 1..=3 {
+        room.join(id).unwrap();
+        room.set_ready(id, true).unwrap();
+    }
+    let number = room.try_countdown().unwrap();
+    room.begin(number).unwrap();
+    // Joined during the match, only watches
+    room.join(4).unwrap();
+    rooms.insert(String::from("room"), room);
+
+    assert_eq!(opponents(&rooms, 1), vec![2, 3]);
+    assert!(opponents(&rooms, 4).is_empty());
+
+    let room = rooms.get_mut("room").unwrap();
+    assert_eq!(room.eliminate(4), None);
+    assert_eq!(room.eliminate(2), Some(3));
+    assert_eq!(room.eliminate(2), None);
+    assert_eq!(room.finish(), None);
+    assert_eq!(room.alive(), vec![1, 3]);
+
+    assert_eq!(room.eliminate(1), Some(2));
+    assert_eq!(room.finish(), Some(vec![3, 1, 2]));
+    assert_eq!(room.phase(), Phase::Finished);
+    assert!(opponents(&rooms, 1).contains(&4));
+
+    // Readying up again goes back to waiting
+    let room = rooms.get_mut("room").unwrap();
+    room.set_ready(1, true).unwrap();
+    assert_eq!(room.phase(), Phase::Waiting);
+}
@@ -0,0 +1,49 @@
+use back::session::{new_resume_token, resume_token_user, History, HISTORY_SIZE};
+use shared::Frame;
+
+fn frame(i: usize) -> Frame {
+    Frame::Text(i.to_string())
+}
+
+#[test]
+fn tokens() {
+    let token = new_resume_token(42);
+    assert_eq!(resume_token_user(&token), Some(42));
+    assert_ne!(new_resume_token(42), token);
+
+    assert_eq!(resume_token_user("42"), None);
+    assert_eq!(resume_token_user(&"z".repeat(96)), None);
+}
+
+#[test]
+fn replay() {
+    let mut history = History::default();
+    for i in 0..5 {
+        history.push(frame(i));
+    }
+
+    assert_eq!(history.sent(), 5);
+    assert_eq!(history.next_id(), 6);
+    assert_eq!(history.since(3), Some(vec![frame(3), frame(4)]));
+    assert_eq!(history.since(5), Some(Vec::new()));
+    // The client can't have more than what was sent
+    assert_eq!(history.since(6), None);
+}
+
+#[test]
+fn forgets_old_messages() {
+    let mut history = History::default();
+    for i in 0..HISTORY_SIZE + 10 {
+        history.push(frame(i));
+    }
+
+    assert_eq!(history.since(9), None);
+    assert_eq!(
+        history.since(10).map(|frames| frames.len()),
+        Some(HISTORY_SIZE)
+    );
+    assert_eq!(
+        history.since(HISTORY_SIZE as u64 + 9),
+        Some(vec![frame(HISTORY_SIZE + 9)])
+    );
+}
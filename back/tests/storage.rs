@@ -0,0 +1,97 @@
+use back::{
+    rating::Rating,
+    storage::{HighScore, Mode, Replay, Storage},
+};
+
+#[test]
+fn migrations() {
+    let storage = Storage::in_memory().unwrap();
+    assert_eq!(storage.version().unwrap(), 2);
+
+    // Opening an already migrated database does not apply anything twice
+    let path = std::env::temp_dir().join(format!("tetris-test-{}.db", std::process::id()));
+    let path = path.to_str().unwrap();
+    Storage::open(path).unwrap().add_player(1).unwrap();
+    let storage = Storage::open(path).unwrap();
+    assert_eq!(storage.version().unwrap(), 2);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn ratings() {
+    let storage = Storage::in_memory().unwrap();
+    let rating = Rating::new(1600., 120., 0.059);
+
+    assert_eq!(storage.rating(u128::MAX).unwrap(), None);
+    storage.set_rating(u128::MAX, &rating).unwrap();
+    storage.set_rating(u128::MAX, &rating).unwrap();
+    assert_eq!(storage.rating(u128::MAX).unwrap(), Some(rating));
+    assert_eq!(storage.ratings().unwrap().len(), 1);
+}
+
+#[test]
+fn matches() {
+    let mut storage = Storage::in_memory().unwrap();
+
+    let first = storage.add_match(true, &[2, 1]).unwrap();
+    let second = storage.add_match(false, &[3, 1, 2]).unwrap();
+    assert_ne!(first, second);
+    assert_eq!(storage.placements(second).unwrap(), vec![3, 1, 2]);
+}
+
+#[test]
+fn high_scores() {
+    let storage = Storage::in_memory().unwrap();
+    let score = |player_id, points, time_ms| HighScore {
+        player_id,
+        points,
+        lines: 40,
+        time_ms,
+    };
+
+    storage
+        .add_high_score(Mode::Marathon, &score(1, 500, 90_000))
+        .unwrap();
+    storage
+        .add_high_score(Mode::Marathon, &score(2, 1500, 120_000))
+        .unwrap();
+    storage
+        .add_high_score(Mode::Sprint, &score(1, 0, 60_000))
+        .unwrap();
+    storage
+        .add_high_score(Mode::Sprint, &score(2, 0, 45_000))
+        .unwrap();
+
+    let marathon = storage.high_scores(Mode::Marathon, 10, 0).unwrap();
+    assert_eq!(
+        marathon
+            .iter()
+            .map(|score| score.points)
+            .collect::<Vec<_>>(),
+        vec![1500, 500]
+    );
+
+    // Fastest first
+    let sprint = storage.high_scores(Mode::Sprint, 1, 1).unwrap();
+    assert_eq!(sprint, vec![score(1, 0, 60_000)]);
+}
+
+#[test]
+fn replays() {
+    let storage = Storage::in_memory().unwrap();
+
+    let mut game = tetris::Game::new(tetris::Config::default(), tetris::SevenBag::new(u64::MAX));
+    game.input(tetris::Input::HardDrop);
+    game.update(std::time::Duration::from_millis(16));
+
+    let replay = Replay {
+        player_id: 1,
+        seed: u64::MAX,
+        config: game.config().clone(),
+        steps: game.drain_steps().collect(),
+    };
+    let id = storage.add_replay(&replay).unwrap();
+
+    assert_eq!(storage.replay(id).unwrap(), Some(replay));
+    assert_eq!(storage.replay(id + 1).unwrap(), None);
+}
@@ -2,12 +2,16 @@ use std::{
     any::Any,
     net::{Ipv4Addr, SocketAddr, SocketAddrV4},
     sync::Once,
+    time::Duration,
 };
 
 use back::build_rocket;
+use futures::{SinkExt as _, StreamExt as _};
 use rocket::{http::Header, local::asynchronous::Client, Rocket};
-use tokio::net::TcpListener;
-use tokio_tungstenite::connect_async;
+use shared::{ClientMessage, Envelope, RoomError, ServerMessage};
+use tetris::Step;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
 use logger::{Config, Output};
 
 type Port = u16;
@@ -16,6 +20,8 @@ async fn start_test() -> Port {
     static INIT: Once = Once::new();
     // Void any logs made using loggers
     INIT.call_once(|| {
+        // Nothing the tests do should end up on disk
+        std::env::set_var("ROCKET_DATABASE", back::storage::IN_MEMORY);
         logger::init(
             Config::default()
                 .level(log::LevelFilter::Off)
@@ -50,6 +56,12 @@ async fn start_test() -> Port {
     let rocket = Rocket::custom(rocket.config())
         .mount("/", rocket.routes().cloned().collect::<Vec<_>>())
         .manage(back::routes::UserMap::default())
+        .manage(back::room::RoomMap::default())
+        .manage(back::queue::SharedQueue::default())
+        .manage(back::rating::RatingMap::default())
+        .manage(back::storage::Db::new(tokio::sync::Mutex::new(
+            back::storage::Storage::in_memory().unwrap(),
+        )))
         .configure(f)
         .ignite()
         .await
@@ -111,3 +123,67 @@ async fn test2() {
 
     println!("Response: {response:?}");
 }
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// A guest, and the client's copy of the game the server started for it
+async fn join(port: Port) -> (Socket, tetris::Game) {
+    let (mut socket, _) = connect_async(format!("ws://127.0.0.1:{port}/ws?codec=json"))
+        .await
+        .unwrap();
+
+    loop {
+        let message = socket.next().await.unwrap().unwrap();
+        let envelope = Envelope::<ServerMessage>::from_text(message.to_text().unwrap()).unwrap();
+        if let ServerMessage::Start { seed, config } = envelope.payload {
+            return (socket, tetris::Game::new(config, tetris::SevenBag::new(seed)));
+        }
+    }
+}
+
+// Sends the message and gives back what the server answers
+async fn send(socket: &mut Socket, message: ClientMessage) -> ServerMessage {
+    let envelope = Envelope::new(1, None, message);
+    socket.send(Message::text(envelope.to_text())).await.unwrap();
+
+    let message = socket.next().await.unwrap().unwrap();
+    Envelope::<ServerMessage>::from_text(message.to_text().unwrap()).unwrap().payload
+}
+
+#[rocket::async_test]
+async fn desync() {
+    let port = start_test().await;
+
+    // The board the client claims does not match the steps
+    let (mut socket, mut game) = join(port).await;
+    let steps = vec![Step::Update(Duration::from_millis(16))];
+    steps.iter().cloned().for_each(|step| game.step(step));
+    let checksum = game.board().checksum() ^ 1;
+    let message = ClientMessage::Steps { steps, checksum };
+    assert_eq!(send(&mut socket, message).await, ServerMessage::Desync);
+
+    // Garbage the server never sent
+    let (mut socket, mut game) = join(port).await;
+    let steps = vec![Step::Garbage { lines: 2, hole: 4 }];
+    steps.iter().cloned().for_each(|step| game.step(step));
+    let checksum = game.board().checksum();
+    let message = ClientMessage::Steps { steps, checksum };
+    assert_eq!(send(&mut socket, message).await, ServerMessage::Desync);
+
+    // More time than the server saw passing
+    let (mut socket, mut game) = join(port).await;
+    let steps = vec![Step::Update(back::routes::MAX_DRIFT + Duration::from_secs(1))];
+    steps.iter().cloned().for_each(|step| game.step(step));
+    let checksum = game.board().checksum();
+    let message = ClientMessage::Steps { steps, checksum };
+    assert_eq!(send(&mut socket, message).await, ServerMessage::Desync);
+}
+
+#[rocket::async_test]
+async fn ranked_guest() {
+    let port = start_test().await;
+
+    let (mut socket, _) = join(port).await;
+    let message = ClientMessage::JoinQueue { players: 2, ranked: true };
+    assert_eq!(send(&mut socket, message).await, ServerMessage::RoomError(RoomError::LoginRequired));
+}
@@ -1,20 +1,25 @@
-use std::{cell::RefCell, rc::Rc};
-
 use gloo::{events::EventListener, utils::window};
 use js_sys::wasm_bindgen::JsCast as _;
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
-use yew::{function_component, html, use_effect, use_state, Html};
+use yew::{function_component, html, use_effect, use_node_ref, use_state, Html};
 
 #[derive(yew::Properties, PartialEq)]
 pub struct Props {
-    pub board: Rc<RefCell<tetris::Board>>,
-    pub floating_piece: tetris::Piece,
-    pub floating_piece_pos: tetris::Position,
+    pub board: tetris::Board,
+    // None for the boards of the others, the server only sends their locked pieces
+    pub floating_piece: Option<(tetris::Piece, tetris::Position)>,
+    // None when the ghost piece is disabled
+    pub ghost_pos: Option<tetris::Position>,
+    pub hold: Option<tetris::PieceId>,
+    pub can_hold: bool,
+    pub next: Vec<tetris::PieceId>,
 }
 
 #[function_component]
 pub fn Board(props: &Props) -> Html {
     let node_ref = yew::html::NodeRef::default();
+    let hold_ref = use_node_ref();
+    let next_ref = use_node_ref();
     let is_first_render = use_state(|| true);
     let display_size = use_state(|| (300, 150));
     let size_listen_event_state = use_state(|| EventListener::new(&window(), "resize", |_| ()));
@@ -24,9 +29,21 @@ pub fn Board(props: &Props) -> Html {
         let display_size = display_size.clone();
         let board = props.board.clone();
         let floating_piece = props.floating_piece.clone();
-        let floating_piece_pos = props.floating_piece_pos;
+        let ghost_pos = props.ghost_pos;
+        let hold_ref = hold_ref.clone();
+        let hold = props.hold;
+        let can_hold = props.can_hold;
+        let next_ref = next_ref.clone();
+        let next = props.next.clone();
 
         use_effect(move || {
+            let width = window()
+                .inner_width()
+                .ok()
+                .and_then(|s| s.as_f64())
+                .unwrap()
+                / 4.;
+
             if let Some(canvas) = node_ref.cast::<HtmlCanvasElement>() {
                 if *is_first_render {
                     is_first_render.set(false);
@@ -46,40 +63,41 @@ pub fn Board(props: &Props) -> Html {
                 render(
                     &canvas,
                     &board,
-                    &floating_piece,
-                    &floating_piece_pos,
-                    window()
-                        .inner_width()
-                        .ok()
-                        .and_then(|s| s.as_f64())
-                        .unwrap()
-                        / 4.,
+                    floating_piece.as_ref(),
+                    ghost_pos,
+                    width,
                 )
             }
 
+            if let Some(canvas) = hold_ref.cast::<HtmlCanvasElement>() {
+                // Greyed out while the hold has already been used for this piece
+                render_side_panel(&canvas, hold.as_slice(), !can_hold, width / 10.);
+            }
+
+            if let Some(canvas) = next_ref.cast::<HtmlCanvasElement>() {
+                render_side_panel(&canvas, &next, false, width / 10.);
+            }
+
             || ()
         });
     }
-    html! { <>
+    html! { <div class="board">
+        <canvas class="board-hold" ref={hold_ref}></canvas>
         <canvas ref={node_ref}></canvas>
-    </>}
+        <canvas class="board-next" ref={next_ref}></canvas>
+    </div>}
 }
 
 fn render(
     canvas: &HtmlCanvasElement,
-    board: &Rc<RefCell<tetris::Board>>,
-    floating_piece: &tetris::Piece,
-    floating_piece_pos: &tetris::Position,
+    board: &tetris::Board,
+    floating_piece: Option<&(tetris::Piece, tetris::Position)>,
+    ghost_pos: Option<tetris::Position>,
     w: f64,
 ) {
     // Stroke line with should not be odd values, source: https://stackoverflow.com/a/10003573
 
-    let ctx: CanvasRenderingContext2d = canvas
-        .get_context("2d")
-        .ok()
-        .flatten()
-        .and_then(|obj| obj.dyn_into().ok())
-        .unwrap();
+    let ctx = context_2d(canvas);
 
     let h = w / 0.5;
 
@@ -92,7 +110,71 @@ fn render(
 
     render_board(&ctx, board, w, h);
 
-    draw_piece(&ctx, floating_piece, *floating_piece_pos, w, h);
+    if let Some((piece, position)) = floating_piece {
+        if let Some(ghost_pos) = ghost_pos {
+            draw_ghost(&ctx, piece, ghost_pos, w, h);
+        }
+
+        draw_piece(&ctx, piece, *position, w, h);
+    }
+
+    // Borders
+    ctx.begin_path();
+    ctx.set_stroke_style_str("white");
+    ctx.set_line_width(2.);
+    ctx.stroke_rect(1., 1., w - 2., h - 2.);
+    ctx.close_path();
+}
+
+// Pieces stacked vertically, each centered in its own box, used for the hold box and the next queue
+fn render_side_panel(
+    canvas: &HtmlCanvasElement,
+    pieces: &[tetris::PieceId],
+    dimmed: bool,
+    cell_size: f64,
+) {
+    // In cells, every piece fits in a 4x2 area, this leaves a bit of room around them
+    const BOX_COLS: f64 = 5.;
+    const BOX_ROWS: f64 = 3.;
+
+    let ctx = context_2d(canvas);
+
+    let w = BOX_COLS * cell_size;
+    let h = BOX_ROWS * cell_size * pieces.len().max(1) as f64;
+
+    canvas.set_width(w as u32);
+    canvas.set_height(h as u32);
+
+    ctx.clear_rect(0.0, 0.0, w, h);
+
+    if dimmed {
+        ctx.set_global_alpha(0.4);
+    }
+
+    for (i, id) in pieces.iter().enumerate() {
+        let piece = tetris::Piece::from(*id);
+
+        let min_x = piece.bits().iter().map(|bit| bit.x()).min().unwrap() as f64;
+        let max_x = piece.bits().iter().map(|bit| bit.x()).max().unwrap() as f64;
+        let min_y = piece.bits().iter().map(|bit| bit.y()).min().unwrap() as f64;
+        let max_y = piece.bits().iter().map(|bit| bit.y()).max().unwrap() as f64;
+
+        let offset_x = (BOX_COLS - (max_x - min_x + 1.)) / 2. - min_x;
+        let offset_y = i as f64 * BOX_ROWS + (BOX_ROWS - (max_y - min_y + 1.)) / 2. - min_y;
+
+        ctx.set_fill_style_str(&piece.color().to_rgba_string());
+
+        for bit in piece.bits() {
+            ctx.fill_rect(
+                (bit.x() as f64 + offset_x) * cell_size,
+                (bit.y() as f64 + offset_y) * cell_size,
+                cell_size,
+                cell_size,
+            );
+        }
+    }
+
+    ctx.set_global_alpha(1.);
 
     // Borders
     ctx.begin_path();
@@ -102,14 +184,23 @@ fn render(
     ctx.close_path();
 }
 
-fn render_board(ctx: &CanvasRenderingContext2d, board: &Rc<RefCell<tetris::Board>>, width: f64, height: f64) {
+fn context_2d(canvas: &HtmlCanvasElement) -> CanvasRenderingContext2d {
+    canvas
+        .get_context("2d")
+        .ok()
+        .flatten()
+        .and_then(|obj| obj.dyn_into().ok())
+        .unwrap()
+}
+
+fn render_board(ctx: &CanvasRenderingContext2d, board: &tetris::Board, width: f64, height: f64) {
     let cols = 10;
     let rows = 20;
 
     let cell_size_x = width / cols as f64;
     let cell_size_y = height / rows as f64;
 
-    for (bit_opt, pos) in board.borrow().iter() {
+    for (bit_opt, pos) in board.iter() {
         let Some(bit) = bit_opt else { continue };
 
         ctx.set_fill_style_str(&bit.color().to_rgba_string());
@@ -176,3 +267,29 @@ fn draw_piece(
         cell_size_y,
     );
 }
+
+// Translucent copy of the piece where it would land
+fn draw_ghost(
+    ctx: &CanvasRenderingContext2d,
+    piece: &tetris::Piece,
+    position: tetris::Position,
+    width: f64,
+    height: f64,
+) {
+    let cols = 10;
+    let rows = 20;
+
+    let cell_size_x = width / cols as f64;
+    let cell_size_y = height / rows as f64;
+
+    ctx.set_global_alpha(0.3);
+    ctx.set_fill_style_str(&piece.color().to_rgba_string());
+
+    for bit in piece.bits().iter() {
+        let x = (position.x() as f64 + bit.x() as f64) * cell_size_x;
+        let y = (position.y() as f64 + bit.y() as f64) * cell_size_y;
+        ctx.fill_rect(x, y, cell_size_x, cell_size_y);
+    }
+
+    ctx.set_global_alpha(1.);
+}
@@ -1,82 +1,290 @@
-use super::{Board as BoardComp, ReactorControlSignal};
-use gloo::timers::callback::Interval;
-use shared::ClientMessage;
-use std::{cell::RefCell, rc::Rc, time::Duration};
-use tetris::Board as TBoard;
+use super::{push_notification, Board as BoardComp, Identicon, Notification, ReactorControlSignal};
+use crate::input::InputHandler;
+use gloo::{
+    events::{EventListener, EventListenerOptions},
+    timers::callback::Interval,
+    utils::window,
+};
+use js_sys::wasm_bindgen::JsCast as _;
+use shared::{ClientMessage, NameError, PlayerInfo, ServerMessage};
+use std::{cell::RefCell, rc::Rc};
 use wasm_timer::Instant;
+use web_sys::KeyboardEvent;
 use yew::{
     function_component, html, use_effect_with, use_force_update, use_mut_ref, use_state, Html,
-    UseStateHandle,
 };
 use yew_agent::reactor::UseReactorSubscriptionHandle;
 
+// ~60 updates per second
+const TICK_INTERVAL_MS: u32 = 16;
+// Steps are sent in batches, ~10 per second
+const STEPS_INTERVAL_MS: u64 = 100;
+// Rows of the board, the garbage meter is as tall as the board
+const BOARD_HEIGHT: u32 = 20;
+
+#[derive(yew::Properties, PartialEq)]
+pub struct Props {
+    // Only the player's own game listens to the keyboard
+    #[prop_or_default]
+    pub keyboard: bool,
+}
+
 #[function_component]
-pub fn Game() -> Html {
+pub fn Game(props: &Props) -> Html {
     let reactor_sub = yew_agent::reactor::use_reactor_subscription::<crate::component::WsReactor>();
     reactor_sub.send(ReactorControlSignal::Start);
 
-    let next_piece_id = use_state(tetris::PieceId::random);
-    let next_piece_pos = use_state(|| tetris::Position::from((5, 5)));
-
-    let board = use_mut_ref(TBoard::default);
-    let last_tick = use_state(Instant::now);
+    let settings = use_state(crate::settings::Settings::load);
+    let game = {
+        let level = settings.level;
+        use_mut_ref(move || {
+            tetris::Game::new(
+                tetris::Config {
+                    level,
+                    ..Default::default()
+                },
+                tetris::SevenBag::new(random_seed()),
+            )
+        })
+    };
+    let input = use_mut_ref(InputHandler::default);
+    let last_tick = use_mut_ref(Instant::now);
+    let last_sent = use_mut_ref(Instant::now);
+    // Reactor outputs already looked at
+    let received = use_mut_ref(|| 0usize);
+    // The server sent the game to play, from then on it checks every step
+    let online = use_mut_ref(|| false);
+    // How the server shows this player to the others
+    let player = use_mut_ref(|| None::<PlayerInfo>);
     let fu = use_force_update();
 
-    if last_tick.elapsed() > Duration::from_millis(450) {
-        game_tick(
-            reactor_sub,
-            &board,
-            next_piece_id.clone(),
-            next_piece_pos.clone(),
-        );
-        last_tick.set(Instant::now());
+    {
+        let game = game.clone();
+        let input = input.clone();
+        let handling = settings.handling.clone();
+        use_effect_with((), move |_| {
+            let interval = Interval::new(TICK_INTERVAL_MS, move || {
+                let now = Instant::now();
+                let delta = now - std::mem::replace(&mut *last_tick.borrow_mut(), now);
+
+                let mut game = game.borrow_mut();
+                input.borrow_mut().update(delta, &handling, &mut game);
+                game.update(delta);
+
+                fu.force_update();
+            });
+            move || drop(interval)
+        });
     }
 
-    use_effect_with((), move |_| {
-        Interval::new(500, move || {
-            fu.force_update();
-        })
-        .forget();
-        || ()
+    {
+        let game = game.clone();
+        let keybinds = settings.keybinds.clone();
+        use_effect_with(props.keyboard, move |keyboard| {
+            let listeners = keyboard.then(|| keyboard_listeners(game, input, keybinds));
+            move || drop(listeners)
+        });
+    }
+
+    // Only the keyboard controlled game is the player's, the others stay offline
+    if props.keyboard {
+        receive_messages(&reactor_sub, &game, &received, &online, &player);
+    }
+    send_steps(reactor_sub, &game, &last_sent, *online.borrow());
+
+    let game = game.borrow();
+
+    // Ready garbage is drawn at the bottom, it's what enters on the next lock
+    let (ready, waiting) = game
+        .garbage()
+        .iter()
+        .fold((0, 0), |(ready, waiting), garbage| {
+            if garbage.is_ready() {
+                (ready + garbage.lines, waiting)
+            } else {
+                (ready, waiting + garbage.lines)
+            }
+        });
+    let ready = ready.min(BOARD_HEIGHT);
+    let waiting = waiting.min(BOARD_HEIGHT - ready);
+
+    let label = player.borrow().as_ref().map(|player| {
+        html! {<div class="game-player">
+            <Identicon identicon={player.identicon} />
+            <span>{ player.name.clone() }</span>
+        </div>}
     });
 
-    html! {<>
-        <BoardComp board={board} floating_piece={tetris::Piece::from(*next_piece_id)} floating_piece_pos={*next_piece_pos}/>
-    </>}
+    html! {<div class="game">
+        { label }
+        <div class="game-field">
+            <div class="garbage-meter">
+                <div class="garbage-waiting" style={format!("height: {}%", waiting * 100 / BOARD_HEIGHT)} />
+                <div class="garbage-ready" style={format!("height: {}%", ready * 100 / BOARD_HEIGHT)} />
+            </div>
+            <BoardComp
+                board={game.board().clone()}
+                floating_piece={Some((game.piece().clone(), game.position()))}
+                ghost_pos={settings.ghost_piece.then(|| game.ghost_position())}
+                hold={game.hold()}
+                can_hold={game.can_hold()}
+                next={game.queue().iter().copied().collect::<Vec<_>>()}
+            />
+        </div>
+        <div class="hud">
+            <span>{ format!("Score {}", game.score().points()) }</span>
+            <span>{ format!("Level {}", game.score().level()) }</span>
+            <span>{ format!("Lines {}", game.score().lines()) }</span>
+        </div>
+    </div>}
 }
 
-fn game_tick(
-    websocket: UseReactorSubscriptionHandle<crate::component::WsReactor>,
-    board: &Rc<RefCell<tetris::Board>>,
-    next_piece_id: UseStateHandle<tetris::PieceId>,
-    next_piece_pos: UseStateHandle<tetris::Position>,
-) {
-    // debug!("Tick");
-
-    let next_pos = (next_piece_pos.x(), next_piece_pos.y() + 1).into();
-
-    let board_ref = board.borrow();
-
-    if board_ref.can_place_at(&tetris::Piece::from(*next_piece_id), &next_pos) {
-        next_piece_pos.set(next_pos);
-    } else {
-        'place: {
-            drop(board_ref);
-            let mut board_ref = board.borrow_mut();
-            if let Err(e) =
-                board_ref.place_at(&tetris::Piece::from(*next_piece_id), &next_piece_pos)
-            {
-                error!(format!("{e}"));
-                break 'place;
+fn keyboard_listeners(
+    game: Rc<RefCell<tetris::Game>>,
+    input: Rc<RefCell<InputHandler>>,
+    keybinds: crate::input::Keybinds,
+) -> [EventListener; 3] {
+    let w = window();
+
+    let keydown = {
+        let input = input.clone();
+        let keybinds = keybinds.clone();
+
+        EventListener::new_with_options(
+            &w,
+            "keydown",
+            EventListenerOptions::enable_prevent_default(),
+            move |event| {
+                let Some(event) = event.dyn_ref::<KeyboardEvent>() else {
+                    return;
+                };
+
+                let Some(action) = keybinds.action(&event.code()) else {
+                    return;
+                };
+
+                // Bound keys should not scroll the page
+                event.prevent_default();
+
+                // The input handler does its own repeating
+                if event.repeat() {
+                    return;
+                }
+
+                input.borrow_mut().press(action, &mut game.borrow_mut());
+            },
+        )
+    };
+
+    let keyup = {
+        let input = input.clone();
+
+        EventListener::new(&w, "keyup", move |event| {
+            let Some(event) = event.dyn_ref::<KeyboardEvent>() else {
+                return;
+            };
+
+            if let Some(action) = keybinds.action(&event.code()) {
+                input.borrow_mut().release(action);
             }
+        })
+    };
 
-            debug!("Board update, sending to reactor");
-            websocket.send(ReactorControlSignal::WsMessage(ClientMessage::BoardUpdate(
-                board_ref.clone(),
-            )));
+    let blur = EventListener::new(&w, "blur", move |_| input.borrow_mut().release_all());
+
+    [keydown, keyup, blur]
+}
 
-            next_piece_id.set(tetris::PieceId::random());
-            next_piece_pos.set((5, 0).into());
+fn random_seed() -> u64 {
+    (js_sys::Math::random() * u64::MAX as f64) as u64
+}
+
+fn receive_messages(
+    websocket: &UseReactorSubscriptionHandle<crate::component::WsReactor>,
+    game: &Rc<RefCell<tetris::Game>>,
+    received: &Rc<RefCell<usize>>,
+    online: &Rc<RefCell<bool>>,
+    player: &Rc<RefCell<Option<PlayerInfo>>>,
+) {
+    let mut received = received.borrow_mut();
+
+    for output in websocket.iter().skip(*received) {
+        match &output.payload {
+            ServerMessage::Hello { version } if *version != shared::PROTOCOL_VERSION => {
+                push_notification(Notification::error(
+                    "Outdated game",
+                    vec!["The server runs another version, reload the page to play online."],
+                    10.,
+                ));
+            }
+            ServerMessage::Welcome(info) => {
+                *player.borrow_mut() = Some(info.clone());
+            }
+            // Welcome already came with the name given instead
+            ServerMessage::NameRefused(e) => {
+                let reason = match e {
+                    NameError::Length => "Names are 3 to 16 characters long.",
+                    NameError::Characters => "Names only use letters, digits, spaces, '_' and '-'.",
+                    NameError::Profanity => "That name is not allowed.",
+                };
+                let playing_as = player
+                    .borrow()
+                    .as_ref()
+                    .map(|info| format!("You play as {} for now, change it in the settings.", info.name))
+                    .unwrap_or_default();
+                push_notification(Notification::info("Name refused", vec![reason, &playing_as], 10.));
+            }
+            ServerMessage::Start { seed, config } => {
+                debug!(format!("Starting the server's game, seed: {seed}"));
+                *game.borrow_mut() = tetris::Game::new(config.clone(), tetris::SevenBag::new(*seed));
+                *online.borrow_mut() = true;
+            }
+            // The game is replaced once the match starts, steps sent until then would not match it
+            ServerMessage::Countdown(_) => {
+                *online.borrow_mut() = false;
+            }
+            ServerMessage::Garbage { lines, hole, .. } => {
+                game.borrow_mut().receive_garbage(*lines, *hole);
+            }
+            ServerMessage::Desync => {
+                error!("The server's simulation does not match this game anymore");
+                *online.borrow_mut() = false;
+            }
+            _ => (),
         }
     }
+
+    *received = websocket.len();
+}
+
+// The server replays the steps and broadcasts what they did, offline games just drop them
+fn send_steps(
+    websocket: UseReactorSubscriptionHandle<crate::component::WsReactor>,
+    game: &Rc<RefCell<tetris::Game>>,
+    last_sent: &Rc<RefCell<Instant>>,
+    online: bool,
+) {
+    let mut game = game.borrow_mut();
+    game.drain_events().for_each(drop);
+
+    if !online {
+        game.drain_steps().for_each(drop);
+        return;
+    }
+    // The others see the board a bit later, but the server gets far fewer messages
+    if last_sent.borrow().elapsed() < std::time::Duration::from_millis(STEPS_INTERVAL_MS) {
+        return;
+    }
+
+    let steps = game.drain_steps().collect::<Vec<_>>();
+    if steps.is_empty() {
+        return;
+    }
+    *last_sent.borrow_mut() = Instant::now();
+
+    let checksum = game.board().checksum();
+    websocket.send(ReactorControlSignal::WsMessage(ClientMessage::Steps {
+        steps,
+        checksum,
+    }));
 }
@@ -0,0 +1,28 @@
+use yew::{function_component, html, Html};
+
+#[derive(yew::Properties, PartialEq)]
+pub struct Props {
+    pub identicon: shared::Identicon,
+}
+
+// One square per filled cell, in the identicon's color
+#[function_component]
+pub fn Identicon(props: &Props) -> Html {
+    let size = shared::Identicon::SIZE;
+    let cells = (0..size)
+        .flat_map(|y| (0..size).map(move |x| (x, y)))
+        .filter(|(x, y)| props.identicon.is_filled(*x, *y))
+        .map(|(x, y)| html! { <rect x={x.to_string()} y={y.to_string()} width="1" height="1" /> })
+        .collect::<Html>();
+
+    html! {
+        <svg
+            class="identicon"
+            viewBox={format!("0 0 {size} {size}")}
+            fill={format!("hsl({}, 65%, 55%)", props.identicon.hue)}
+            shape-rendering="crispEdges"
+        >
+            { cells }
+        </svg>
+    }
+}
@@ -0,0 +1,301 @@
+use super::{Identicon, ReactorControlSignal, WsReactor};
+use shared::{ClientMessage, PlayerInfo, RoomError, RoomInfo, ServerMessage, SoloMode};
+use std::collections::HashSet;
+use web_sys::HtmlInputElement;
+use yew::{
+    function_component, html, html::TargetCast as _, use_effect_with, use_mut_ref, use_state,
+    Callback, Html, InputEvent,
+};
+
+// Room size picked by default when creating one
+const DEFAULT_CAPACITY: usize = 4;
+// Match sizes offered for quick play
+const QUICK_PLAY_SIZES: [usize; 2] = [2, 4];
+const SOLO_MODES: [(SoloMode, &str); 2] = [(SoloMode::Marathon, "Marathon"), (SoloMode::Sprint, "Sprint")];
+
+struct Room {
+    name: String,
+    // In join order
+    users: Vec<PlayerInfo>,
+    ready: HashSet<u128>,
+    // Seconds left before the match starts
+    countdown: Option<u32>,
+    // Of the last match, winner first
+    placements: Vec<u128>,
+}
+
+// What the server told about rooms so far
+#[derive(Default)]
+struct State {
+    // This user, as the server sees it
+    me: Option<u128>,
+    rooms: Vec<RoomInfo>,
+    room: Option<Room>,
+    // Size of the quick play match the user is waiting for
+    queued: Option<usize>,
+    error: Option<RoomError>,
+}
+
+impl State {
+    fn receive(&mut self, message: &ServerMessage) {
+        match message {
+            ServerMessage::Welcome(info) => self.me = Some(info.user_id),
+            // A new user on the server's side, in no room
+            ServerMessage::ResumeFailed => *self = Self::default(),
+            ServerMessage::RoomList(rooms) => self.rooms = rooms.clone(),
+            ServerMessage::RoomJoined { name, users } => {
+                self.error = None;
+                self.queued = None;
+                self.room = Some(Room {
+                    name: name.clone(),
+                    users: users.clone(),
+                    ready: HashSet::new(),
+                    countdown: None,
+                    placements: Vec::new(),
+                });
+            }
+            ServerMessage::RoomLeft => self.room = None,
+            ServerMessage::RoomError(e) => self.error = Some(*e),
+            ServerMessage::Queued { players } => self.queued = Some(*players),
+            ServerMessage::QueueLeft => self.queued = None,
+            _ => (),
+        }
+
+        let Some(room) = self.room.as_mut() else {
+            return;
+        };
+        match message {
+            ServerMessage::UserJoined(info) => room.users.push(info.clone()),
+            ServerMessage::UserLeft(user_id) => {
+                room.users.retain(|info| info.user_id != *user_id);
+                room.ready.remove(user_id);
+                room.countdown = None;
+            }
+            ServerMessage::ReadyChanged { user_id, ready } => {
+                if *ready {
+                    room.ready.insert(*user_id);
+                } else {
+                    room.ready.remove(user_id);
+                    room.countdown = None;
+                }
+            }
+            ServerMessage::Countdown(seconds) => room.countdown = Some(*seconds),
+            // Everyone has to ready up again for the next one
+            ServerMessage::Start { .. } => {
+                room.countdown = None;
+                room.ready.clear();
+                room.placements.clear();
+            }
+            ServerMessage::MatchResult { placements } => room.placements = placements.clone(),
+            _ => (),
+        }
+    }
+}
+
+fn error_text(error: RoomError) -> &'static str {
+    match error {
+        RoomError::InvalidName => "Room names can't be empty or longer than 32 characters",
+        RoomError::AlreadyExists => "A room with that name already exists",
+        RoomError::NotFound => "That room does not exist anymore",
+        RoomError::Full => "That room is full",
+        RoomError::AlreadyInRoom => "Leave your room first",
+        RoomError::NotInRoom => "You are not in a room",
+        RoomError::MatchInProgress => "Wait for the match to end",
+        RoomError::LoginRequired => "Log in to play ranked matches",
+    }
+}
+
+// Rooms to create and join, and the players of the one the user is in
+#[function_component]
+pub fn Lobby() -> Html {
+    let reactor_sub = yew_agent::reactor::use_reactor_subscription::<WsReactor>();
+    reactor_sub.send(ReactorControlSignal::Start);
+
+    let state = use_mut_ref(State::default);
+    // Reactor outputs already looked at
+    let received = use_mut_ref(|| 0usize);
+    let room_name = use_state(String::new);
+    let capacity = use_state(|| DEFAULT_CAPACITY);
+
+    {
+        let mut state = state.borrow_mut();
+        let mut received = received.borrow_mut();
+        for output in reactor_sub.iter().skip(*received) {
+            state.receive(&output.payload);
+        }
+        *received = reactor_sub.len();
+    }
+
+    let send = {
+        let reactor_sub = reactor_sub.clone();
+        Callback::from(move |msg: ClientMessage| reactor_sub.send(ReactorControlSignal::WsMessage(msg)))
+    };
+
+    {
+        let send = send.clone();
+        use_effect_with((), move |_| send.emit(ClientMessage::ListRooms));
+    }
+
+    let state = state.borrow();
+    let error = state
+        .error
+        .map(|error| html! { <p class="lobby-error">{ error_text(error) }</p> });
+
+    let Some(room) = state.room.as_ref() else {
+        let rooms = state
+            .rooms
+            .iter()
+            .map(|room| {
+                let join = {
+                    let send = send.clone();
+                    let name = room.name.clone();
+                    Callback::from(move |_| send.emit(ClientMessage::JoinRoom(name.clone())))
+                };
+
+                html! {<tr>
+                    <td>{ room.name.clone() }</td>
+                    <td>{ format!("{}/{}", room.players, room.capacity) }</td>
+                    <td><button onclick={join} disabled={room.players >= room.capacity}>{ "Join" }</button></td>
+                </tr>}
+            })
+            .collect::<Html>();
+
+        let refresh = {
+            let send = send.clone();
+            Callback::from(move |_| send.emit(ClientMessage::ListRooms))
+        };
+        let set_name = {
+            let room_name = room_name.clone();
+            Callback::from(move |e: InputEvent| {
+                let input: HtmlInputElement = e.target_unchecked_into();
+                room_name.set(input.value());
+            })
+        };
+        let set_capacity = {
+            let capacity = capacity.clone();
+            Callback::from(move |e: web_sys::Event| {
+                let input: HtmlInputElement = e.target_unchecked_into();
+                if let Ok(value) = input.value().parse() {
+                    capacity.set(value);
+                }
+            })
+        };
+        let create = {
+            let send = send.clone();
+            let (room_name, capacity) = (room_name.clone(), capacity.clone());
+            Callback::from(move |_| {
+                send.emit(ClientMessage::CreateRoom {
+                    name: (*room_name).clone(),
+                    capacity: *capacity,
+                })
+            })
+        };
+
+        // A room is made for the match once the server finds enough players
+        let quick_play = match state.queued {
+            Some(players) => {
+                let cancel = {
+                    let send = send.clone();
+                    Callback::from(move |_| send.emit(ClientMessage::LeaveQueue))
+                };
+                html! {<p>
+                    { format!("Looking for a {players} players match... ") }
+                    <button onclick={cancel}>{ "Cancel" }</button>
+                </p>}
+            }
+            None => QUICK_PLAY_SIZES
+                .into_iter()
+                .map(|players| {
+                    let join = {
+                        let send = send.clone();
+                        Callback::from(move |_| send.emit(ClientMessage::JoinQueue { players, ranked: false }))
+                    };
+                    let label = if players == 2 { "1v1".to_string() } else { format!("{players} players") };
+                    html! { <button onclick={join}>{ label }</button> }
+                })
+                .chain(std::iter::once({
+                    // Always a 1v1, against someone with a close rating
+                    let join = {
+                        let send = send.clone();
+                        Callback::from(move |_| send.emit(ClientMessage::JoinQueue { players: 2, ranked: true }))
+                    };
+                    html! { <button onclick={join}>{ "Ranked 1v1" }</button> }
+                }))
+                .collect::<Html>(),
+        };
+
+        // The server answers with a new game, only these go on the leaderboard
+        let solo = SOLO_MODES
+            .into_iter()
+            .map(|(mode, label)| {
+                let play = {
+                    let send = send.clone();
+                    Callback::from(move |_| send.emit(ClientMessage::PlaySolo(mode)))
+                };
+                html! { <button onclick={play}>{ label }</button> }
+            })
+            .collect::<Html>();
+
+        return html! {<div class="lobby">
+            <h2>{ "Solo" }</h2>
+            { solo }
+            <h2>{ "Quick play" }</h2>
+            { quick_play }
+            <h2>{ "Rooms" }</h2>
+            <table>{ rooms }</table>
+            <button onclick={refresh}>{ "Refresh" }</button>
+            <div class="lobby-create">
+                <input type="text" maxlength="32" placeholder="Room name" value={(*room_name).clone()} oninput={set_name} />
+                <input type="number" min="2" max="16" value={capacity.to_string()} onchange={set_capacity} />
+                <button onclick={create} disabled={room_name.trim().is_empty()}>{ "Create" }</button>
+            </div>
+            { error }
+        </div>};
+    };
+
+    let ready = state.me.is_some_and(|me| room.ready.contains(&me));
+    let toggle_ready = {
+        let send = send.clone();
+        Callback::from(move |_| send.emit(ClientMessage::Ready(!ready)))
+    };
+    let leave = {
+        let send = send.clone();
+        Callback::from(move |_| {
+            send.emit(ClientMessage::LeaveRoom);
+            send.emit(ClientMessage::ListRooms);
+        })
+    };
+
+    let users = room
+        .users
+        .iter()
+        .map(|user| {
+            let place = room
+                .placements
+                .iter()
+                .position(|id| *id == user.user_id)
+                .map(|place| format!("#{}", place + 1));
+
+            html! {<tr>
+                <td><Identicon identicon={user.identicon} /></td>
+                <td>{ user.name.clone() }</td>
+                <td>{ if room.ready.contains(&user.user_id) { "Ready" } else { "" } }</td>
+                <td>{ place }</td>
+            </tr>}
+        })
+        .collect::<Html>();
+
+    let status = match room.countdown {
+        Some(seconds) => format!("Starting in {seconds}..."),
+        None => "The match starts once everyone is ready".to_string(),
+    };
+
+    html! {<div class="lobby">
+        <h2>{ room.name.clone() }</h2>
+        <table>{ users }</table>
+        <p>{ status }</p>
+        <button onclick={toggle_ready}>{ if ready { "Not ready" } else { "Ready" } }</button>
+        <button onclick={leave}>{ "Leave" }</button>
+        { error }
+    </div>}
+}
@@ -11,3 +11,9 @@ mod board;
 pub use board::Board;
 mod game;
 pub use game::Game;
+mod identicon;
+pub use identicon::Identicon;
+mod lobby;
+pub use lobby::Lobby;
+mod opponents;
+pub use opponents::Opponents;
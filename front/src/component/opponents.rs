@@ -0,0 +1,86 @@
+use super::{Board as BoardComp, Identicon, ReactorControlSignal, WsReactor};
+use shared::{ClientMessage, PlayerInfo, ServerMessage};
+use std::collections::HashMap;
+use yew::{function_component, html, use_mut_ref, Html};
+
+// The others in the room, and their boards as the server last sent them
+#[derive(Default)]
+struct State {
+    me: Option<u128>,
+    // In join order
+    players: Vec<PlayerInfo>,
+    boards: HashMap<u128, tetris::Board>,
+}
+
+impl State {
+    fn receive(&mut self, message: &ServerMessage) {
+        match message {
+            ServerMessage::Welcome(info) => self.me = Some(info.user_id),
+            ServerMessage::ResumeFailed | ServerMessage::RoomLeft => {
+                self.players.clear();
+                self.boards.clear();
+            }
+            ServerMessage::RoomJoined { users, .. } => {
+                self.players = users.iter().filter(|info| Some(info.user_id) != self.me).cloned().collect();
+                self.boards.clear();
+            }
+            ServerMessage::UserJoined(info) => self.players.push(info.clone()),
+            ServerMessage::UserLeft(user_id) => {
+                self.players.retain(|info| info.user_id != *user_id);
+                self.boards.remove(user_id);
+            }
+            // Everyone starts from an empty board
+            ServerMessage::Start { .. } => self.boards.clear(),
+            ServerMessage::Broadcast { user_id, msg } => {
+                if let ClientMessage::BoardUpdate(board) = msg.as_ref() {
+                    self.boards.insert(*user_id, board.clone());
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+// Boards of the other players of the room, labelled with their names
+#[function_component]
+pub fn Opponents() -> Html {
+    let reactor_sub = yew_agent::reactor::use_reactor_subscription::<WsReactor>();
+    reactor_sub.send(ReactorControlSignal::Start);
+
+    let state = use_mut_ref(State::default);
+    // Reactor outputs already looked at
+    let received = use_mut_ref(|| 0usize);
+
+    {
+        let mut state = state.borrow_mut();
+        let mut received = received.borrow_mut();
+        for output in reactor_sub.iter().skip(*received) {
+            state.receive(&output.payload);
+        }
+        *received = reactor_sub.len();
+    }
+
+    let state = state.borrow();
+    state
+        .players
+        .iter()
+        .map(|player| {
+            let board = state.boards.get(&player.user_id).cloned().unwrap_or_default();
+
+            html! {<div class="game">
+                <div class="game-player">
+                    <Identicon identicon={player.identicon} />
+                    <span>{ player.name.clone() }</span>
+                </div>
+                <BoardComp
+                    board={board}
+                    floating_piece={None}
+                    ghost_pos={None}
+                    hold={None}
+                    can_hold={true}
+                    next={Vec::new()}
+                />
+            </div>}
+        })
+        .collect::<Html>()
+}
@@ -4,6 +4,7 @@ use futures::{lock::Mutex, sink::SinkExt};
 use futures::{FutureExt, StreamExt};
 use gloo::net::websocket::{futures::WebSocket, Message};
 use serde::{Deserialize, Serialize};
+use shared::{ClientMessage, Codec, Envelope, Frame, ServerMessage, PROTOCOL_VERSION};
 use yew::platform::time::sleep;
 use yew_agent::reactor::{reactor, ReactorScope};
 
@@ -16,8 +17,73 @@ pub enum ReactorControlSignal {
 
 static LOCK: LazyLock<Mutex<i32>> = LazyLock::new(|| Mutex::new(0));
 
+// Waits between reconnection attempts, doubled after each failed one
+const FIRST_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+// What's needed to get the server's user back after the socket drops
+#[derive(Default)]
+struct Session {
+    token: Option<String>,
+    // Picked from the settings for a new session, a resumed one has to keep it
+    codec: Codec,
+    // Id of the last server message got, the server sends the ones after it again
+    last_id: u64,
+    // Id of the last message sent to the server
+    sent: u64,
+    // Sent while disconnected, they go out once the session is resumed
+    outbox: Vec<ClientMessage>,
+}
+
+enum Ended {
+    Stopped,
+    Dropped,
+}
+
+fn ws_url(codec: Codec) -> String {
+    let mut url = format!("ws://127.0.0.1:42071/ws?codec={}", codec.as_str());
+
+    // The server gives another name if this one is too long, too short or rude
+    let name = crate::settings::Settings::load().name;
+    if !name.is_empty() {
+        url.push_str(&format!("&name={}", js_sys::encode_uri_component(&name)));
+    }
+    url
+}
+
+async fn send(ws: &mut WebSocket, session: &mut Session, msg: ClientMessage) -> Result<(), String> {
+    session.sent += 1;
+    let message = match Envelope::new(session.sent, None, msg).encode(session.codec) {
+        Frame::Text(text) => Message::Text(text),
+        Frame::Binary(bytes) => Message::Bytes(bytes),
+    };
+
+    ws.send(message)
+        .await
+        .map_err(|e| format!("Failed to send message to websocket due to: {e}"))
+}
+
+// Passes a server message on to the subscribers, keeping track of what resuming needs
+async fn deliver(
+    scope: &mut ReactorScope<ReactorControlSignal, Envelope<ServerMessage>>,
+    session: &mut Session,
+    envelope: Envelope<ServerMessage>,
+) {
+    if let ServerMessage::ResumeToken(token) = &envelope.payload {
+        session.token = Some(token.clone());
+    }
+    // 0 is only about the socket
+    if envelope.id != 0 {
+        session.last_id = envelope.id;
+    }
+
+    if let Err(e) = scope.send(envelope).await {
+        error!(format!("Failed to send a message to the scope due to: {e}"));
+    }
+}
+
 #[reactor]
-pub async fn WsReactor(mut scope: ReactorScope<ReactorControlSignal, String>) {
+pub async fn WsReactor(mut scope: ReactorScope<ReactorControlSignal, Envelope<ServerMessage>>) {
     debug!("Plop");
     // I want the worker to stop if it does not receive a start command in the first second of it's lifetime
     let mut count = 0;
@@ -49,22 +115,69 @@ pub async fn WsReactor(mut scope: ReactorScope<ReactorControlSignal, String>) {
 
     debug!("Starting ws");
 
-    let mut ws = match WebSocket::open("ws://127.0.0.1:42071/ws") {
-        Ok(ws) => {
-            error!("New Ws has been created");
-            ws
+    *lock += 1;
+
+    drop(lock);
+
+    let mut session = Session::default();
+    let mut backoff = FIRST_BACKOFF;
+    loop {
+        // The server replays the missed messages as they were encoded
+        if session.token.is_none() {
+            session.codec = crate::settings::Settings::load().codec;
         }
-        Err(e) => {
-            error!(format!("Cannot open websocket due to: {e}"));
-            return;
+        match WebSocket::open(&ws_url(session.codec)) {
+            Ok(ws) => {
+                debug!("New Ws has been created");
+                match connection(ws, &mut scope, &mut session, &mut backoff).await {
+                    Ended::Stopped => return,
+                    Ended::Dropped => warn!(format!("Lost the websocket, reconnecting in {backoff:?}")),
+                }
+            }
+            Err(e) => error!(format!("Cannot open websocket due to: {e}")),
         }
-    };
 
-    *lock += 1;
+        // Messages sent in the meantime wait for the next connection
+        let mut wait = Box::pin(sleep(backoff).fuse());
+        loop {
+            futures::select! {
+                _ = wait => break,
+                rcs = scope.next() => match rcs {
+                    Some(ReactorControlSignal::Stop) => return,
+                    Some(ReactorControlSignal::WsMessage(msg)) => session.outbox.push(msg),
+                    Some(ReactorControlSignal::Start) | None => (),
+                },
+            }
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
 
-    drop(lock);
+// Runs a socket until it's stopped or lost
+// After a lost one, the new socket resumes the session first, what the server sends before it answers is kept aside
+async fn connection(
+    mut ws: WebSocket,
+    scope: &mut ReactorScope<ReactorControlSignal, Envelope<ServerMessage>>,
+    session: &mut Session,
+    backoff: &mut Duration,
+) -> Ended {
+    let mut pending = match session.token.clone() {
+        Some(token) => {
+            let resume = ClientMessage::Resume { token, last_id: session.last_id };
+            if let Err(e) = send(&mut ws, session, resume).await {
+                error!(e);
+                return Ended::Dropped;
+            }
+            Some(Vec::new())
+        }
+        None => {
+            // Nothing to resume, whatever was played offline means nothing to the server
+            session.outbox.clear();
+            None
+        }
+    };
 
-    'inner: loop {
+    loop {
         futures::select! {
             rcs = scope.next() => match rcs{
                 Some(ReactorControlSignal::Start) => {
@@ -74,22 +187,17 @@ pub async fn WsReactor(mut scope: ReactorScope<ReactorControlSignal, String>) {
                     if let Err(e) = ws.close(Some(1000 /*Normal closure*/), None){
                         error!(format!("Failed to close the websocket due to: {e}"))
                     };
-                    break 'inner;
+                    return Ended::Stopped;
                 }
                 Some(ReactorControlSignal::WsMessage(msg)) => {
+                    if pending.is_some() {
+                        session.outbox.push(msg);
+                        continue;
+                    }
+
                     warn!("Received message from game, sending to server");
-                    let msg_string = match serde_json::to_string(&msg) {
-                        Ok(s) => s,
-                        Err(e) => {
-                            error!(format!("Failed to serialize message: {msg:?} due to: {e}"));
-                            if let Err(e) = scope.send(format!("Failed to serialize message: {msg:?} due to: {e}")).await{
-                                error!(format!("Failed to send back error to scope due to: {e}"));
-                            };
-                            continue 'inner;
-                        }
-                    };
-                    if let Err(e) = ws.send(Message::Text(msg_string)).await {
-                        scope.send(format!("Failed to send message to websocket due to: {e}")).await.unwrap();
+                    if let Err(e) = send(&mut ws, session, msg).await {
+                        error!(e);
                     }
                 }
                 None => {
@@ -98,15 +206,62 @@ pub async fn WsReactor(mut scope: ReactorScope<ReactorControlSignal, String>) {
             },
             ws_message_opt = ws.next().fuse() => match ws_message_opt{
                 Some(Ok(msg)) => {
-                    debug!(format!("Ws received: {msg:?}"));
-                    scope.send(format!("New message: {msg:?}")).await.unwrap();
+                    *backoff = FIRST_BACKOFF;
+
+                    let frame = match msg {
+                        Message::Text(text) => Frame::Text(text),
+                        Message::Bytes(bytes) => Frame::Binary(bytes),
+                    };
+                    let envelope = match Envelope::<ServerMessage>::decode(&frame) {
+                        Ok(envelope) => envelope,
+                        Err(e) => {
+                            error!(format!("Failed to parse server message due to: {e}"));
+                            continue;
+                        }
+                    };
+                    debug!(format!("Ws received: {envelope:?}"));
+
+                    match envelope.payload {
+                        // Subscribers get it too, to tell the player to reload
+                        ServerMessage::Hello { version } if version != PROTOCOL_VERSION => {
+                            error!(format!("The server speaks version {version}, this client {PROTOCOL_VERSION}"));
+                            deliver(scope, session, envelope).await;
+                            return Ended::Stopped;
+                        }
+                        ServerMessage::Resumed => {
+                            debug!("Session resumed");
+                            pending = None;
+                            for msg in std::mem::take(&mut session.outbox) {
+                                if let Err(e) = send(&mut ws, session, msg).await {
+                                    error!(e);
+                                }
+                            }
+                        }
+                        // A new session, the messages kept aside are its first ones
+                        // Subscribers get it too, the rooms and games they knew about are gone
+                        ServerMessage::ResumeFailed => {
+                            warn!("Failed to resume the session, starting a new one");
+                            session.last_id = 0;
+                            session.outbox.clear();
+                            let pending = pending.take().unwrap_or_default();
+                            deliver(scope, session, envelope).await;
+                            for envelope in pending {
+                                deliver(scope, session, envelope).await;
+                            }
+                        }
+                        _ => match pending.as_mut() {
+                            Some(pending) => pending.push(envelope),
+                            None => deliver(scope, session, envelope).await,
+                        },
+                    }
                 }
                 Some(Err(e)) => {
                     error!(format!("Ws received a message but an error occured: {e}"));
-                    break 'inner;
+                    return Ended::Dropped;
                 }
                 None => {
                     debug!("Received nothing");
+                    return Ended::Dropped;
                 }
             },
         }
@@ -0,0 +1,262 @@
+use std::{collections::HashMap, time::Duration};
+use tetris::{Input, Rotation};
+
+// Wide enough to cross the whole board
+const BOARD_WIDTH: usize = 10;
+const BOARD_HEIGHT: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateClockwise,
+    RotateCounterClockwise,
+    Rotate180,
+    Hold,
+}
+
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::SoftDrop,
+        Self::HardDrop,
+        Self::RotateClockwise,
+        Self::RotateCounterClockwise,
+        Self::Rotate180,
+        Self::Hold,
+    ];
+
+    fn input(&self) -> Input {
+        match self {
+            Self::MoveLeft => Input::MoveLeft,
+            Self::MoveRight => Input::MoveRight,
+            Self::SoftDrop => Input::SoftDrop,
+            Self::HardDrop => Input::HardDrop,
+            Self::RotateClockwise => Input::Rotate(Rotation::Clockwise),
+            Self::RotateCounterClockwise => Input::Rotate(Rotation::CounterClockwise),
+            Self::Rotate180 => Input::Rotate(Rotation::Half),
+            Self::Hold => Input::Hold,
+        }
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MoveLeft => write!(f, "Move left"),
+            Self::MoveRight => write!(f, "Move right"),
+            Self::SoftDrop => write!(f, "Soft drop"),
+            Self::HardDrop => write!(f, "Hard drop"),
+            Self::RotateClockwise => write!(f, "Rotate clockwise"),
+            Self::RotateCounterClockwise => write!(f, "Rotate counter-clockwise"),
+            Self::Rotate180 => write!(f, "Rotate 180"),
+            Self::Hold => write!(f, "Hold"),
+        }
+    }
+}
+
+// KeyboardEvent.code -> action, codes are physical keys so this works the same on every layout
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Keybinds(HashMap<String, Action>);
+
+impl Keybinds {
+    pub fn action(&self, code: &str) -> Option<Action> {
+        self.0.get(code).copied()
+    }
+
+    // Sorted, so the settings page does not shuffle them around
+    pub fn keys(&self, action: Action) -> Vec<&str> {
+        let mut keys = self
+            .0
+            .iter()
+            .filter(|(_, a)| **a == action)
+            .map(|(code, _)| code.as_str())
+            .collect::<Vec<&str>>();
+        keys.sort();
+        keys
+    }
+
+    // A key only ever does one thing, binding it again replaces its previous action
+    pub fn bind(&mut self, code: String, action: Action) {
+        self.0.insert(code, action);
+    }
+
+    pub fn unbind(&mut self, code: &str) {
+        self.0.remove(code);
+    }
+}
+
+impl Default for Keybinds {
+    // Guideline defaults
+    fn default() -> Self {
+        Self(HashMap::from(
+            [
+                ("ArrowLeft", Action::MoveLeft),
+                ("ArrowRight", Action::MoveRight),
+                ("ArrowDown", Action::SoftDrop),
+                ("Space", Action::HardDrop),
+                ("ArrowUp", Action::RotateClockwise),
+                ("KeyX", Action::RotateClockwise),
+                ("KeyZ", Action::RotateCounterClockwise),
+                ("ControlLeft", Action::RotateCounterClockwise),
+                ("KeyA", Action::Rotate180),
+                ("KeyC", Action::Hold),
+                ("ShiftLeft", Action::Hold),
+            ]
+            .map(|(code, action)| (code.to_string(), action)),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Handling {
+    // Delayed auto shift, how long a direction has to be held before it starts repeating
+    pub das: Duration,
+    // Auto repeat rate, time between two repeated moves, zero sends the piece straight to the wall
+    pub arr: Duration,
+    // Soft drop factor, how many times faster than gravity a soft drop is
+    pub sdf: u32,
+}
+
+impl Handling {
+    // Factors from this one up drop the piece to the floor instantly
+    pub const INSTANT_SDF: u32 = 41;
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Self {
+            das: Duration::from_millis(167),
+            arr: Duration::from_millis(33),
+            sdf: 20,
+        }
+    }
+}
+
+// Turns key presses and releases into game inputs, repeating them as the handling says
+#[derive(Debug, Default)]
+pub struct InputHandler {
+    left: bool,
+    right: bool,
+    // Most recently pressed horizontal direction, that's the one that moves when both are held
+    direction: Option<Action>,
+    das_timer: Duration,
+    arr_timer: Duration,
+    soft_drop: bool,
+    soft_drop_timer: Duration,
+}
+
+impl InputHandler {
+    pub fn press(&mut self, action: Action, game: &mut tetris::Game) {
+        match action {
+            Action::MoveLeft | Action::MoveRight => {
+                if action == Action::MoveLeft {
+                    self.left = true;
+                } else {
+                    self.right = true;
+                }
+
+                self.direction = Some(action);
+                self.das_timer = Duration::ZERO;
+                self.arr_timer = Duration::ZERO;
+
+                game.input(action.input());
+            }
+            Action::SoftDrop => {
+                self.soft_drop = true;
+                self.soft_drop_timer = Duration::ZERO;
+
+                game.input(action.input());
+            }
+            _ => game.input(action.input()),
+        }
+    }
+
+    pub fn release(&mut self, action: Action) {
+        match action {
+            Action::MoveLeft | Action::MoveRight => {
+                let other = if action == Action::MoveLeft {
+                    self.left = false;
+                    (Action::MoveRight, self.right)
+                } else {
+                    self.right = false;
+                    (Action::MoveLeft, self.left)
+                };
+
+                if self.direction != Some(action) {
+                    return;
+                }
+
+                // Fall back to the other direction if it's still held, it has to charge again
+                self.direction = match other {
+                    (other, true) => Some(other),
+                    (_, false) => None,
+                };
+                self.das_timer = Duration::ZERO;
+                self.arr_timer = Duration::ZERO;
+            }
+            Action::SoftDrop => self.soft_drop = false,
+            _ => (),
+        }
+    }
+
+    // When the page loses focus, we won't receive the key releases
+    pub fn release_all(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn update(&mut self, delta: Duration, handling: &Handling, game: &mut tetris::Game) {
+        if let Some(direction) = self.direction {
+            let charged_before = self.das_timer >= handling.das;
+            let repeating_before = self.das_timer.saturating_sub(handling.das);
+
+            self.das_timer += delta;
+
+            if self.das_timer >= handling.das {
+                if handling.arr.is_zero() {
+                    for _ in 0..BOARD_WIDTH {
+                        game.input(direction.input());
+                    }
+                } else {
+                    // The first repeat happens as soon as the DAS is charged
+                    if !charged_before {
+                        game.input(direction.input());
+                    }
+
+                    self.arr_timer +=
+                        self.das_timer.saturating_sub(handling.das) - repeating_before;
+
+                    while self.arr_timer >= handling.arr {
+                        self.arr_timer -= handling.arr;
+                        game.input(direction.input());
+                    }
+                }
+            }
+        }
+
+        if self.soft_drop {
+            if handling.sdf >= Handling::INSTANT_SDF {
+                for _ in 0..BOARD_HEIGHT {
+                    game.input(Input::SoftDrop);
+                }
+                return;
+            }
+
+            let interval = game.gravity() / handling.sdf.max(1);
+            if interval.is_zero() {
+                return;
+            }
+
+            self.soft_drop_timer += delta;
+
+            while self.soft_drop_timer >= interval {
+                self.soft_drop_timer -= interval;
+                game.input(Input::SoftDrop);
+            }
+        }
+    }
+}
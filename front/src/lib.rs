@@ -4,7 +4,9 @@ extern crate gloo_console;
 
 mod app;
 pub mod component;
+mod input;
 mod scene;
+mod settings;
 
 #[derive(Debug, Clone, Copy, PartialEq, yew_router::Routable)]
 pub enum Route {
@@ -12,6 +14,10 @@ pub enum Route {
     Default,
     #[at("/home")]
     Home,
+    #[at("/settings")]
+    Settings,
+    #[at("/leaderboard")]
+    Leaderboard,
     #[not_found]
     #[at("/404")]
     NotFound,
@@ -32,8 +38,24 @@ pub fn Router() -> yew::Html {
                     Route::Default | Route::Home => {
                         (vec![
                             Scene::Home,
+                            Scene::Settings,
+                            Scene::Leaderboard,
                         ],0)
                     }
+                    Route::Settings => {
+                        (vec![
+                            Scene::Home,
+                            Scene::Settings,
+                            Scene::Leaderboard,
+                        ],1)
+                    }
+                    Route::Leaderboard => {
+                        (vec![
+                            Scene::Home,
+                            Scene::Settings,
+                            Scene::Leaderboard,
+                        ],2)
+                    }
                     Route::NotFound => {
                         (vec![
                             Scene::NotFound
@@ -1,4 +1,4 @@
-use crate::component::Game;
+use crate::component::{Game, Lobby, Opponents};
 use yew::{function_component, html, Html};
 
 #[derive(yew::Properties, std::cmp::PartialEq)]
@@ -17,8 +17,9 @@ pub fn Home(_props: &Props) -> Html {
     // reactor_sub.send(crate::component::ReactorControlSignal::Start);
 
     html! { <>
-        <Game />
-        <Game />
+        <Lobby />
+        <Game keyboard={true} />
+        <Opponents />
         // <button onclick={start}>{ "Start ws" }</button>
     </>}
 }
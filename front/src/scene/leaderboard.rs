@@ -0,0 +1,157 @@
+use crate::component::{ReactorControlSignal, WsReactor};
+use gloo::net::http::Request;
+use shared::{LeaderBoard, LeaderBoardMode, ServerMessage, LEADERBOARD_PAGE_SIZE};
+use yew::{
+    function_component, html, platform::spawn_local, use_effect_with, use_mut_ref, use_state,
+    Callback, Html,
+};
+
+// Fetched from the back, `page` starts at 1
+async fn fetch(mode: LeaderBoardMode, page: usize) -> Result<LeaderBoard, gloo::net::Error> {
+    Request::get(&format!("/api/leaderboard?mode={}&page={page}", mode.as_str()))
+        .send()
+        .await?
+        .json::<LeaderBoard>()
+        .await
+}
+
+fn user_name(user_id: u128) -> String {
+    // Ids are huge, the start is enough to tell players apart
+    format!("#{}", &format!("{user_id:032x}")[..8])
+}
+
+fn format_time(ms: u64) -> String {
+    format!("{}:{:02}.{:03}", ms / 60_000, ms / 1000 % 60, ms % 1000)
+}
+
+#[function_component]
+pub fn Leaderboard() -> Html {
+    if let Some(nav) = yew_router::hooks::use_navigator() {
+        nav.replace(&crate::Route::Leaderboard)
+    } else {
+        error!("Failed to retrieve the navigator")
+    }
+
+    let reactor_sub = yew_agent::reactor::use_reactor_subscription::<WsReactor>();
+    reactor_sub.send(ReactorControlSignal::Start);
+
+    let mode = use_state(|| LeaderBoardMode::Ranked);
+    let page = use_state(|| 1usize);
+    let board = use_state(|| None::<LeaderBoard>);
+    // Bumped to fetch the current page again
+    let refresh = use_state(|| 0u32);
+    // Reactor outputs already looked at
+    let received = use_mut_ref(|| 0usize);
+    // Only the last fetch is shown, switching tabs or pages does not wait for the previous one to answer
+    let fetches = use_mut_ref(|| 0u32);
+
+    // Ratings change after every ranked match, the server tells everyone when
+    {
+        let mut received = received.borrow_mut();
+        let update = reactor_sub
+            .iter()
+            .skip(*received)
+            .any(|output| matches!(output.payload, ServerMessage::LeaderBoardUpdate(_)));
+        *received = reactor_sub.len();
+
+        if update && *mode == LeaderBoardMode::Ranked {
+            refresh.set(*refresh + 1);
+        }
+    }
+
+    {
+        let board = board.clone();
+        let fetches = fetches.clone();
+        use_effect_with((*mode, *page, *refresh), move |(mode, page, _)| {
+            let (mode, page) = (*mode, *page);
+            *fetches.borrow_mut() += 1;
+            let fetch_id = *fetches.borrow();
+
+            spawn_local(async move {
+                let result = fetch(mode, page).await;
+                if *fetches.borrow() != fetch_id {
+                    return;
+                }
+
+                match result {
+                    Ok(new) => board.set(Some(new)),
+                    Err(e) => error!(format!("Failed to fetch the leaderboard: {e}")),
+                }
+            });
+        });
+    }
+
+    let tabs = LeaderBoardMode::ALL
+        .into_iter()
+        .map(|tab| {
+            let onclick = {
+                let mode = mode.clone();
+                let page = page.clone();
+                Callback::from(move |_| {
+                    mode.set(tab);
+                    page.set(1);
+                })
+            };
+
+            html! {
+                <button class={if *mode == tab { "leaderboard-tab current" } else { "leaderboard-tab" }} {onclick}>
+                    { tab.as_str() }
+                </button>
+            }
+        })
+        .collect::<Html>();
+
+    let first = (*page - 1) * LEADERBOARD_PAGE_SIZE;
+    let rows = match &*board {
+        None => html! { <tr><td colspan="4">{ "Loading..." }</td></tr> },
+        Some(LeaderBoard::Ratings(entries)) => entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                html! {<tr>
+                    <td>{ first + i + 1 }</td>
+                    <td>{ user_name(entry.user_id) }</td>
+                    <td>{ entry.rating }</td>
+                    <td>{ format!("± {}", entry.deviation * 2) }</td>
+                </tr>}
+            })
+            .collect::<Html>(),
+        Some(LeaderBoard::Scores(entries)) => entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                html! {<tr>
+                    <td>{ first + i + 1 }</td>
+                    <td>{ user_name(entry.user_id) }</td>
+                    <td>{ if *mode == LeaderBoardMode::Sprint { format_time(entry.time_ms) } else { entry.points.to_string() } }</td>
+                    <td>{ format!("{} lines", entry.lines) }</td>
+                </tr>}
+            })
+            .collect::<Html>(),
+    };
+
+    // A short page is the last one
+    let last_page = match &*board {
+        Some(LeaderBoard::Ratings(entries)) => entries.len() < LEADERBOARD_PAGE_SIZE,
+        Some(LeaderBoard::Scores(entries)) => entries.len() < LEADERBOARD_PAGE_SIZE,
+        None => true,
+    };
+    let previous = {
+        let page = page.clone();
+        Callback::from(move |_| page.set(page.saturating_sub(1).max(1)))
+    };
+    let next = {
+        let page = page.clone();
+        Callback::from(move |_| page.set(*page + 1))
+    };
+
+    html! {<div class="leaderboard">
+        <div class="leaderboard-tabs">{ tabs }</div>
+        <table>{ rows }</table>
+        <div class="leaderboard-pages">
+            <button onclick={previous} disabled={*page == 1}>{ "<" }</button>
+            <span>{ format!("Page {}", *page) }</span>
+            <button onclick={next} disabled={last_page}>{ ">" }</button>
+        </div>
+    </div>}
+}
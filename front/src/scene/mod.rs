@@ -4,10 +4,16 @@ pub use not_found::NotFound;
 // pub use wasm::WASM;
 mod home;
 pub use home::Home;
+mod settings;
+pub use settings::Settings;
+mod leaderboard;
+pub use leaderboard::Leaderboard;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Scene {
     Home,
+    Settings,
+    Leaderboard,
     NotFound,
 }
 
@@ -17,6 +23,8 @@ impl Scene {
 
         match self {
             Scene::Home => html! {<Home {current_scene}/>},
+            Scene::Settings => html! {<Settings />},
+            Scene::Leaderboard => html! {<Leaderboard />},
             Scene::NotFound => html! {<NotFound />},
         }
     }
@@ -26,6 +34,8 @@ impl std::fmt::Display for Scene {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Scene::Home => write!(f, "Home"),
+            Scene::Settings => write!(f, "Settings"),
+            Scene::Leaderboard => write!(f, "Leaderboard"),
             Scene::NotFound => write!(f, "Not found"),
         }
     }
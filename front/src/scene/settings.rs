@@ -0,0 +1,270 @@
+use crate::input::{Action, Handling};
+use gloo::{
+    events::{EventListener, EventListenerOptions},
+    utils::window,
+};
+use js_sys::wasm_bindgen::JsCast as _;
+use std::time::Duration;
+use web_sys::{HtmlInputElement, KeyboardEvent};
+use yew::{
+    function_component, html, html::TargetCast as _, use_effect_with, use_state, Callback, Html,
+};
+
+// Numeric field, out of range values are clamped instead of refused
+fn number_input(value: u64, min: u64, max: u64, onchange: Callback<u64>) -> Html {
+    let onchange = Callback::from(move |e: web_sys::Event| {
+        let input: HtmlInputElement = e.target_unchecked_into();
+
+        if let Ok(value) = input.value().parse::<u64>() {
+            onchange.emit(value.clamp(min, max));
+        }
+    });
+
+    html! {
+        <input
+            type="number"
+            min={min.to_string()}
+            max={max.to_string()}
+            value={value.to_string()}
+            {onchange}
+        />
+    }
+}
+
+#[function_component]
+pub fn Settings() -> Html {
+    if let Some(nav) = yew_router::hooks::use_navigator() {
+        nav.replace(&crate::Route::Settings)
+    } else {
+        error!("Failed to retrieve the navigator")
+    }
+
+    let settings = use_state(crate::settings::Settings::load);
+    // Action waiting for a key press to be bound
+    let listening = use_state(|| None::<Action>);
+
+    // Every change is saved right away, there is no apply button
+    let update = {
+        let settings = settings.clone();
+        Callback::from(move |new: crate::settings::Settings| {
+            new.save();
+            settings.set(new);
+        })
+    };
+
+    {
+        let settings = settings.clone();
+        let listening = listening.clone();
+        let update = update.clone();
+
+        use_effect_with(*listening, move |action| {
+            let listener = action.map(|action| {
+                EventListener::new_with_options(
+                    &window(),
+                    "keydown",
+                    EventListenerOptions::enable_prevent_default(),
+                    move |event| {
+                        let Some(event) = event.dyn_ref::<KeyboardEvent>() else {
+                            return;
+                        };
+                        event.prevent_default();
+
+                        // Escape cancels, so it can't be bound
+                        if event.code() != "Escape" {
+                            let mut new = (*settings).clone();
+                            new.keybinds.bind(event.code(), action);
+                            update.emit(new);
+                        }
+
+                        listening.set(None);
+                    },
+                )
+            });
+
+            move || drop(listener)
+        });
+    }
+
+    let handling_input = |value: Duration, min: u64, max: u64, set: fn(&mut Handling, Duration)| {
+        let settings = settings.clone();
+        let update = update.clone();
+
+        number_input(
+            value.as_millis() as u64,
+            min,
+            max,
+            Callback::from(move |ms| {
+                let mut new = (*settings).clone();
+                set(&mut new.handling, Duration::from_millis(ms));
+                update.emit(new);
+            }),
+        )
+    };
+
+    let das = handling_input(settings.handling.das, 0, 1000, |h, d| h.das = d);
+    let arr = handling_input(settings.handling.arr, 0, 500, |h, d| h.arr = d);
+
+    let sdf = {
+        let settings = settings.clone();
+        let update = update.clone();
+
+        number_input(
+            settings.handling.sdf as u64,
+            1,
+            Handling::INSTANT_SDF as u64,
+            Callback::from(move |sdf| {
+                let mut new = (*settings).clone();
+                new.handling.sdf = sdf as u32;
+                update.emit(new);
+            }),
+        )
+    };
+
+    let level = {
+        let settings = settings.clone();
+        let update = update.clone();
+
+        number_input(
+            settings.level as u64,
+            1,
+            20,
+            Callback::from(move |level| {
+                let mut new = (*settings).clone();
+                new.level = level as u32;
+                update.emit(new);
+            }),
+        )
+    };
+
+    // Used the next time the websocket connects
+    let name = {
+        let settings = settings.clone();
+        let update = update.clone();
+
+        Callback::from(move |e: web_sys::Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut new = (*settings).clone();
+            new.name = input.value().trim().to_string();
+            update.emit(new);
+        })
+    };
+
+    let toggle_ghost = {
+        let settings = settings.clone();
+        let update = update.clone();
+
+        Callback::from(move |_| {
+            let mut new = (*settings).clone();
+            new.ghost_piece = !new.ghost_piece;
+            update.emit(new);
+        })
+    };
+
+    // Used the next time the websocket connects
+    let toggle_json = {
+        let settings = settings.clone();
+        let update = update.clone();
+
+        Callback::from(move |_| {
+            let mut new = (*settings).clone();
+            new.codec = match new.codec {
+                shared::Codec::Json => shared::Codec::Binary,
+                shared::Codec::Binary => shared::Codec::Json,
+            };
+            update.emit(new);
+        })
+    };
+
+    let reset = {
+        let update = update.clone();
+        Callback::from(move |_| update.emit(crate::settings::Settings::default()))
+    };
+
+    let keybinds = Action::ALL
+        .into_iter()
+        .map(|action| {
+            let keys = settings
+                .keybinds
+                .keys(action)
+                .into_iter()
+                .map(|code| {
+                    let unbind = {
+                        let settings = settings.clone();
+                        let update = update.clone();
+                        let code = code.to_string();
+
+                        Callback::from(move |_| {
+                            let mut new = (*settings).clone();
+                            new.keybinds.unbind(&code);
+                            update.emit(new);
+                        })
+                    };
+
+                    html! {
+                        <button class="settings-key" title="Remove" onclick={unbind}>{ code }</button>
+                    }
+                })
+                .collect::<Html>();
+
+            let listen = {
+                let listening = listening.clone();
+                Callback::from(move |_| listening.set(Some(action)))
+            };
+
+            html! {<tr>
+                <td>{ action.to_string() }</td>
+                <td>
+                    { keys }
+                    <button class="settings-key-add" onclick={listen}>
+                        { if *listening == Some(action) { "Press a key..." } else { "+" } }
+                    </button>
+                </td>
+            </tr>}
+        })
+        .collect::<Html>();
+
+    html! {<div class="settings">
+        <h2>{ "Handling" }</h2>
+        <table>
+            <tr><td>{ "DAS (ms)" }</td><td>{ das }</td></tr>
+            <tr><td>{ "ARR (ms)" }</td><td>{ arr }</td></tr>
+            <tr>
+                <td>{ format!("SDF ({} is instant)", Handling::INSTANT_SDF) }</td>
+                <td>{ sdf }</td>
+            </tr>
+            <tr><td>{ "Starting level" }</td><td>{ level }</td></tr>
+            <tr>
+                <td>{ "Ghost piece" }</td>
+                <td>
+                    <input type="checkbox" checked={settings.ghost_piece} onchange={toggle_ghost} />
+                </td>
+            </tr>
+        </table>
+
+        <h2>{ "Keys" }</h2>
+        <p>{ "Click a key to remove it, press Escape to cancel a binding." }</p>
+        <table>{ keybinds }</table>
+
+        <h2>{ "Guest" }</h2>
+        <table>
+            <tr>
+                <td>{ "Name" }</td>
+                <td>
+                    <input type="text" maxlength="16" placeholder="Guest-1234" value={settings.name.clone()} onchange={name} />
+                </td>
+            </tr>
+        </table>
+
+        <h2>{ "Network" }</h2>
+        <table>
+            <tr>
+                <td>{ "Readable messages (JSON, for debugging)" }</td>
+                <td>
+                    <input type="checkbox" checked={settings.codec == shared::Codec::Json} onchange={toggle_json} />
+                </td>
+            </tr>
+        </table>
+
+        <button onclick={reset}>{ "Reset to defaults" }</button>
+    </div>}
+}
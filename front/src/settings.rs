@@ -0,0 +1,70 @@
+use gloo::utils::window;
+
+const STORAGE_KEY: &str = "settings";
+
+// Player preferences, persisted in the local storage like the theme and the locale
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub ghost_piece: bool,
+    // Level local games start at, higher levels fall faster
+    pub level: u32,
+    pub handling: crate::input::Handling,
+    pub keybinds: crate::input::Keybinds,
+    // Shown to the others when playing as a guest, the server picks one if empty
+    pub name: String,
+    // Binary messages are smaller, JSON ones can be read in the browser's network tab
+    pub codec: shared::Codec,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ghost_piece: true,
+            level: tetris::Config::default().level,
+            handling: crate::input::Handling::default(),
+            keybinds: crate::input::Keybinds::default(),
+            name: String::new(),
+            codec: shared::Codec::Binary,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let Some(saved) = window()
+            .local_storage()
+            .ok()
+            .flatten()
+            .and_then(|s| s.get_item(STORAGE_KEY).ok().flatten())
+        else {
+            return Self::default();
+        };
+
+        serde_json::from_str(&saved).unwrap_or_else(|e| {
+            error!(format!("Failed to read saved settings due to: {e}"));
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        let Some(storage) = window().local_storage().ok().flatten() else {
+            error!("Failed to save settings due to: Could not get the local storage");
+            return;
+        };
+
+        let saved = match serde_json::to_string(self) {
+            Ok(saved) => saved,
+            Err(e) => {
+                error!(format!("Failed to serialize settings due to: {e}"));
+                return;
+            }
+        };
+
+        if let Err(e) = storage.set_item(STORAGE_KEY, &saved) {
+            error!(format!(
+                "Failed to write settings to local storage due to: {e:?}"
+            ));
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use shared::{ClientMessage, Codec, Envelope, Frame, ServerMessage};
+use tetris::{Board, Cell, PieceId, Position};
+
+// A mid game board, the usual BoardUpdate
+fn board() -> Board {
+    let mut board = Board::default();
+    for y in 1..=8u8 {
+        for x in (1..=10u8).filter(|x| *x != y % 10 + 1) {
+            let cell = if y <= 3 { Cell::Garbage } else { Cell::Piece(PieceId::ALL[(x + y) as usize % 7]) };
+            *board.get_mut_at(&Position::from((x, y))).unwrap() = Some(cell);
+        }
+    }
+    board
+}
+
+fn board_update() -> Envelope<ServerMessage> {
+    let msg = Box::new(ClientMessage::BoardUpdate(board()));
+    Envelope::new(42, None, ServerMessage::Broadcast { user_id: u128::MAX / 3, msg })
+}
+
+fn len(frame: &Frame) -> usize {
+    match frame {
+        Frame::Text(text) => text.len(),
+        Frame::Binary(bytes) => bytes.len(),
+    }
+}
+
+fn codec(c: &mut Criterion) {
+    let envelope = board_update();
+
+    // The point of the binary codec, criterion only measures time so it's printed
+    let json = envelope.encode(Codec::Json);
+    let binary = envelope.encode(Codec::Binary);
+    println!("BoardUpdate: {} bytes as JSON, {} bytes as binary", len(&json), len(&binary));
+
+    let mut group = c.benchmark_group("board_update");
+    for codec in [Codec::Json, Codec::Binary] {
+        let frame = envelope.encode(codec);
+
+        group.bench_function(format!("encode {}", codec.as_str()), |b| {
+            b.iter(|| black_box(&envelope).encode(codec))
+        });
+        group.bench_function(format!("decode {}", codec.as_str()), |b| {
+            b.iter(|| Envelope::<ServerMessage>::decode(black_box(&frame)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, codec);
+criterion_main!(benches);
@@ -1,3 +1,136 @@
+// Bumped on every change of the messages, both sides have to agree on it
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// Every websocket message, both ways, is one of these
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct Envelope<T> {
+    pub version: u32,
+    // Counts the messages of the sender, from 1
+    // The server counts per user, across resumes, and uses 0 for the answers that are only about the socket
+    pub id: u64,
+    // Id of the message this one answers
+    pub reply_to: Option<u64>,
+    pub payload: T,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(id: u64, reply_to: Option<u64>, payload: T) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            id,
+            reply_to,
+            payload,
+        }
+    }
+}
+
+impl<T: serde::Serialize> Envelope<T> {
+    pub fn to_text(&self) -> String {
+        // Nothing in the messages can fail to serialize, maps all have string keys
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        postcard::to_allocvec(self).unwrap()
+    }
+
+    pub fn encode(&self, codec: Codec) -> Frame {
+        match codec {
+            Codec::Json => Frame::Text(self.to_text()),
+            Codec::Binary => Frame::Binary(self.to_bytes()),
+        }
+    }
+}
+
+impl<T: serde::de::DeserializeOwned> Envelope<T> {
+    pub fn from_text(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes(bytes)
+    }
+
+    // Whatever the codec of the connection, the frame tells which one it is
+    pub fn decode(frame: &Frame) -> Result<Self, DecodeError> {
+        match frame {
+            Frame::Text(text) => Self::from_text(text).map_err(DecodeError::Json),
+            Frame::Binary(bytes) => Self::from_bytes(bytes).map_err(DecodeError::Binary),
+        }
+    }
+}
+
+// The start of an envelope, read before the payload so a message of another version can still be answered
+#[derive(Debug, Clone, Copy, serde::Deserialize, PartialEq, Eq)]
+pub struct Header {
+    pub version: u32,
+    pub id: u64,
+}
+
+impl Header {
+    // JSON skips the other fields, postcard reads the fields in order and leaves the rest of the bytes alone
+    pub fn decode(frame: &Frame) -> Result<Self, DecodeError> {
+        match frame {
+            Frame::Text(text) => serde_json::from_str(text).map_err(DecodeError::Json),
+            Frame::Binary(bytes) => postcard::take_from_bytes(bytes)
+                .map(|(header, _)| header)
+                .map_err(DecodeError::Binary),
+        }
+    }
+}
+
+// How envelopes are written on a connection, picked by the client when it connects
+// Boards are packed in the binary one, see tetris::Board::pack
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum Codec {
+    #[default]
+    Json,
+    Binary,
+}
+
+impl Codec {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Binary => "binary",
+        }
+    }
+}
+
+impl std::str::FromStr for Codec {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "binary" => Ok(Self::Binary),
+            _ => Err(()),
+        }
+    }
+}
+
+// A websocket message, text for JSON and binary for the binary codec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Json(serde_json::Error),
+    Binary(postcard::Error),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "invalid JSON message: {e}"),
+            Self::Binary(e) => write!(f, "invalid binary message: {e}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 pub enum ClientMessage {
     LinesDestroyed (
@@ -5,11 +138,236 @@ pub enum ClientMessage {
         Vec<u16>
     ),
     BoardUpdate(tetris::Board),
+    // Steps played since the last message and the checksum of the board they lead to
+    // The server replays them, clients don't get to send boards, lines or attacks themselves
+    Steps {
+        steps: Vec<tetris::Step>,
+        checksum: u64,
+    },
     GameOver,
+    // Rooms, a user is in at most one, and only gets the messages of the users in the same room
+    CreateRoom { name: String, capacity: usize },
+    JoinRoom(String),
+    LeaveRoom,
+    ListRooms,
+    // Ready for the next match of the room, it starts once everyone is
+    Ready(bool),
+    // Quick play, the server puts the user in a new room once it finds enough players, 2 for a 1v1
+    // Ranked matches are always 1v1, against someone with a close rating
+    JoinQueue { players: usize, ranked: bool },
+    LeaveQueue,
+    // A new game alone, outside of any room, answered with a Start
+    PlaySolo(SoloMode),
+    // First message of a new socket after losing the previous one, with the ResumeToken of that one
+    // and the id of the last message it got, the server sends the ones after it again
+    Resume { token: String, last_id: u64 },
+}
+
+// Games played alone, the only ones whose scores go on the leaderboard
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum SoloMode {
+    // SPRINT_LINES lines as fast as possible
+    Sprint,
+    // As many points as possible before topping out
+    Marathon,
+}
+
+pub const SPRINT_LINES: u32 = 40;
+
+impl SoloMode {
+    pub fn config(&self) -> tetris::Config {
+        match self {
+            Self::Sprint => tetris::Config {
+                line_goal: Some(SPRINT_LINES),
+                ..Default::default()
+            },
+            Self::Marathon => tetris::Config::default(),
+        }
+    }
+}
+
+// 5x5 avatar mirrored around the middle column, generated from the user id
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct Identicon {
+    // 0 to 359
+    pub hue: u16,
+    // Left 3 columns, row by row, one bit per cell
+    pub cells: u16,
+}
+
+impl Identicon {
+    pub const SIZE: usize = 5;
+
+    pub fn is_filled(&self, x: usize, y: usize) -> bool {
+        // The right columns mirror the left ones
+        let x = x.min(Self::SIZE - 1 - x);
+        self.cells >> (y * 3 + x) & 1 == 1
+    }
+}
+
+// Why a guest's name got refused
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum NameError {
+    Length,
+    // Letters, digits, spaces, '_' and '-'
+    Characters,
+    Profanity,
+}
+
+// What others see of a user, a guest's name is not unique
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct PlayerInfo {
+    pub user_id: u128,
+    pub name: String,
+    pub identicon: Identicon,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct RoomInfo {
+    pub name: String,
+    pub players: usize,
+    pub capacity: usize,
+}
+
+// Glicko-2 rating, rounded
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct LeaderBoardEntry {
+    pub user_id: u128,
+    pub rating: u32,
+    pub deviation: u32,
+}
+
+// Best time for sprints, points for marathons
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct ScoreEntry {
+    pub user_id: u128,
+    pub points: u64,
+    pub lines: u32,
+    pub time_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum LeaderBoardMode {
+    Sprint,
+    Marathon,
+    Ranked,
+}
+
+impl LeaderBoardMode {
+    pub const ALL: [Self; 3] = [Self::Sprint, Self::Marathon, Self::Ranked];
+
+    // As used in `/api/leaderboard?mode=`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sprint => "sprint",
+            Self::Marathon => "marathon",
+            Self::Ranked => "ranked",
+        }
+    }
+}
+
+impl std::str::FromStr for LeaderBoardMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|mode| mode.as_str() == s).ok_or(())
+    }
+}
+
+pub const LEADERBOARD_PAGE_SIZE: usize = 20;
+
+// A page of `/api/leaderboard`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum LeaderBoard {
+    Ratings(Vec<LeaderBoardEntry>),
+    Scores(Vec<ScoreEntry>),
+}
+
+// Body of `/api/register` and `/api/login`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+// Sent back on register and login, the token goes in an `Authorization: Bearer` header, or in `/ws?token=`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct Session {
+    pub player_id: u128,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum AccountError {
+    // 3 to 20 letters, digits, '_' or '-'
+    InvalidUsername,
+    // 8 to 128 characters
+    InvalidPassword,
+    UsernameTaken,
+    // Same error for an unknown username, so they can't be guessed
+    WrongCredentials,
+    Internal,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum RoomError {
+    InvalidName,
+    AlreadyExists,
+    NotFound,
+    Full,
+    AlreadyInRoom,
+    NotInRoom,
+    // Readying up is only possible between matches
+    MatchInProgress,
+    // Ranked matches are for logged in players only
+    LoginRequired,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 pub enum ServerMessage {
-    Broadcast { user_id: u128, msg: ClientMessage },
-    LeaderBoardUpdate(),
+    // First message of every socket, a client that does not speak this version has to be updated
+    Hello { version: u32 },
+    // Answer to a message of another version, it was ignored
+    UnsupportedVersion { supported: u32 },
+    // Sent to every user by the server itself
+    Notice(String),
+    // First message of a connection, how the others see this user
+    Welcome(PlayerInfo),
+    // Follows the Welcome when the name the guest asked for is refused, it plays with the one in there instead
+    NameRefused(NameError),
+    // To get this connection back if it drops, for a while
+    ResumeToken(String),
+    // Answers to a Resume, only about the socket, their id is 0
+    // Resumed is followed by the missed messages, after ResumeFailed the new connection is a new user
+    Resumed,
+    ResumeFailed,
+    // Boxed, a board is much bigger than the other variants
+    Broadcast { user_id: u128, msg: Box<ClientMessage> },
+    // The game to play, the server simulates the same one
+    Start { seed: u64, config: tetris::Config },
+    // Garbage sent by another player, the client has to apply it as a step as soon as it gets it
+    Garbage { from: u128, lines: u32, hole: u8 },
+    // The client's board does not match the simulation, its steps are ignored from now on
+    Desync,
+    RoomList(Vec<RoomInfo>),
+    // Sent to the user that joined, with everyone in the room, itself included
+    RoomJoined { name: String, users: Vec<PlayerInfo> },
+    RoomLeft,
+    RoomError(RoomError),
+    // Sent to the others in the room
+    UserJoined(PlayerInfo),
+    UserLeft(u128),
+    // Match lifecycle, sent to everyone in the room
+    ReadyChanged { user_id: u128, ready: bool },
+    // Seconds left before the match starts, followed by a Start with the seed every player shares
+    Countdown(u32),
+    // Topped out or left, place 1 is the winner
+    Eliminated { user_id: u128, place: usize },
+    // Players of the match, winner first
+    MatchResult { placements: Vec<u128> },
+    // With the size of the match the user is waiting for, a RoomJoined follows once it's found
+    Queued { players: usize },
+    QueueLeft,
+    // Best ranked players first, sent to everyone after each ranked match
+    LeaderBoardUpdate(Vec<LeaderBoardEntry>),
 }
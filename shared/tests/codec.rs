@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests {
+    use proptest::{collection::vec, option, prelude::*};
+    use shared::*;
+    use std::time::Duration;
+    use tetris::{Board, Cell, Input, PieceId, Position, Rotation, Step};
+
+    fn cell() -> impl Strategy<Value = Cell> {
+        prop_oneof![
+            Just(Cell::Piece(PieceId::I)),
+            Just(Cell::Piece(PieceId::O)),
+            Just(Cell::Piece(PieceId::T)),
+            Just(Cell::Piece(PieceId::S)),
+            Just(Cell::Piece(PieceId::Z)),
+            Just(Cell::Piece(PieceId::L)),
+            Just(Cell::Piece(PieceId::J)),
+            Just(Cell::Garbage),
+        ]
+    }
+
+    fn board() -> impl Strategy<Value = Board> {
+        vec(option::of(cell()), 200).prop_map(|cells| {
+            let mut board = Board::default();
+            for (i, cell) in cells.into_iter().enumerate() {
+                let position = Position::from(((i % 10) as u8 + 1, (i / 10) as u8 + 1));
+                *board.get_mut_at(&position).unwrap() = cell;
+            }
+            board
+        })
+    }
+
+    fn step() -> impl Strategy<Value = Step> {
+        let input = prop_oneof![
+            Just(Input::MoveLeft),
+            Just(Input::MoveRight),
+            Just(Input::SoftDrop),
+            Just(Input::HardDrop),
+            Just(Input::Rotate(Rotation::Clockwise)),
+            Just(Input::Rotate(Rotation::CounterClockwise)),
+            Just(Input::Rotate(Rotation::Half)),
+            Just(Input::Hold),
+        ];
+
+        prop_oneof![
+            input.prop_map(Step::Input),
+            any::<u64>().prop_map(|ms| Step::Update(Duration::from_millis(ms))),
+            (any::<u32>(), any::<u8>()).prop_map(|(lines, hole)| Step::Garbage { lines, hole }),
+        ]
+    }
+
+    fn client_message() -> impl Strategy<Value = ClientMessage> {
+        prop_oneof![
+            board().prop_map(ClientMessage::BoardUpdate),
+            vec(any::<u16>(), 0..4).prop_map(ClientMessage::LinesDestroyed),
+            (vec(step(), 0..32), any::<u64>()).prop_map(|(steps, checksum)| ClientMessage::Steps { steps, checksum }),
+            (".*", any::<usize>()).prop_map(|(name, capacity)| ClientMessage::CreateRoom { name, capacity }),
+            ("[0-9a-f]{96}", any::<u64>()).prop_map(|(token, last_id)| ClientMessage::Resume { token, last_id }),
+            Just(ClientMessage::GameOver),
+            prop_oneof![Just(SoloMode::Sprint), Just(SoloMode::Marathon)].prop_map(ClientMessage::PlaySolo),
+        ]
+    }
+
+    fn player() -> impl Strategy<Value = PlayerInfo> {
+        (any::<u128>(), ".*", 0..360u16, 0..0x8000u16).prop_map(|(user_id, name, hue, cells)| PlayerInfo {
+            user_id,
+            name,
+            identicon: Identicon { hue, cells },
+        })
+    }
+
+    fn server_message() -> impl Strategy<Value = ServerMessage> {
+        prop_oneof![
+            (any::<u128>(), client_message())
+                .prop_map(|(user_id, msg)| ServerMessage::Broadcast { user_id, msg: Box::new(msg) }),
+            (any::<u128>(), any::<u32>(), any::<u8>())
+                .prop_map(|(from, lines, hole)| ServerMessage::Garbage { from, lines, hole }),
+            (".*", vec(player(), 0..16)).prop_map(|(name, users)| ServerMessage::RoomJoined { name, users }),
+            vec(any::<u128>(), 0..16).prop_map(|placements| ServerMessage::MatchResult { placements }),
+            player().prop_map(ServerMessage::Welcome),
+            any::<u32>().prop_map(|version| ServerMessage::Hello { version }),
+        ]
+    }
+
+    fn envelope<T: std::fmt::Debug>(payload: impl Strategy<Value = T>) -> impl Strategy<Value = Envelope<T>> {
+        (any::<u64>(), option::of(any::<u64>()), payload)
+            .prop_map(|(id, reply_to, payload)| Envelope::new(id, reply_to, payload))
+    }
+
+    proptest! {
+        #[test]
+        fn client_round_trip(envelope in envelope(client_message())) {
+            for codec in [Codec::Json, Codec::Binary] {
+                prop_assert_eq!(&Envelope::decode(&envelope.encode(codec)).unwrap(), &envelope);
+            }
+        }
+
+        #[test]
+        fn server_round_trip(envelope in envelope(server_message())) {
+            for codec in [Codec::Json, Codec::Binary] {
+                prop_assert_eq!(&Envelope::decode(&envelope.encode(codec)).unwrap(), &envelope);
+            }
+        }
+
+        #[test]
+        fn packed_boards(board in board()) {
+            prop_assert_eq!(Board::unpack(&board.pack()), Some(board));
+        }
+    }
+
+    #[test]
+    fn json_boards_are_rows() {
+        // The board is still readable in JSON, only the binary codec packs it
+        let text = serde_json::to_string(&Board::default()).unwrap();
+        assert_eq!(text, serde_json::to_string(&[[None::<Cell>; 10]; 20]).unwrap());
+    }
+
+    #[test]
+    fn codecs() {
+        assert_eq!("binary".parse(), Ok(Codec::Binary));
+        assert_eq!(Codec::Json.as_str().parse(), Ok(Codec::Json));
+        assert_eq!("bson".parse::<Codec>(), Err(()));
+
+        let text = Envelope::new(1, None, ServerMessage::Desync).encode(Codec::Json);
+        assert!(matches!(text, Frame::Text(_)));
+        assert!(Envelope::<ServerMessage>::decode(&Frame::Binary(b"{}".to_vec())).is_err());
+    }
+}
@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod tests {
+    use shared::*;
+
+    #[test]
+    fn round_trip() {
+        let envelope = Envelope::new(3, Some(2), ServerMessage::RoomError(RoomError::Full));
+        let text = envelope.to_text();
+
+        assert_eq!(Envelope::<ServerMessage>::from_text(&text).unwrap(), envelope);
+        assert_eq!(envelope.version, PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn borrowed_payload() {
+        // The server sends references, clients read owned messages
+        let message = ClientMessage::JoinQueue { players: 2, ranked: true };
+        let text = Envelope::new(1, None, &message).to_text();
+
+        assert_eq!(Envelope::<ClientMessage>::from_text(&text).unwrap().payload, message);
+    }
+
+    #[test]
+    fn wrong_payload() {
+        let text = Envelope::new(1, None, ClientMessage::LeaveQueue).to_text();
+
+        assert!(Envelope::<ServerMessage>::from_text(&text).is_err());
+        assert!(Envelope::<ServerMessage>::from_text("Broadcast: {}").is_err());
+    }
+
+    #[test]
+    fn header_of_another_version() {
+        // Whatever a newer client sends, the version and id can be read to answer it
+        let envelope = Envelope {
+            version: PROTOCOL_VERSION + 1,
+            id: 7,
+            reply_to: None,
+            // Past the last variant, in both codecs
+            payload: u32::MAX,
+        };
+        let header = Header {
+            version: PROTOCOL_VERSION + 1,
+            id: 7,
+        };
+
+        for codec in [Codec::Json, Codec::Binary] {
+            let frame = envelope.encode(codec);
+            assert_eq!(Header::decode(&frame).unwrap(), header);
+            assert!(Envelope::<ClientMessage>::decode(&frame).is_err());
+        }
+    }
+}
@@ -1,10 +1,52 @@
-pub type BoardRow = [Option<crate::PieceId>; 10];
+// What a filled cell of the board holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Cell {
+    Piece(crate::PieceId),
+    // Sent by an opponent, never part of a piece
+    Garbage,
+}
+
+impl Cell {
+    pub fn color(&self) -> crate::Color {
+        match self {
+            Self::Piece(id) => id.color(),
+            Self::Garbage => crate::Color::from((128, 128, 128)),
+        }
+    }
+}
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+impl From<crate::PieceId> for Cell {
+    fn from(id: crate::PieceId) -> Self {
+        Self::Piece(id)
+    }
+}
+
+pub type BoardRow = [Option<Cell>; 10];
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Board([BoardRow; 20]);
 
+// Cells packed 5 at a time in a little endian u16, as base 9 digits, the first cell being the lowest digit
+// 3 bits are not enough for the 8 kinds of cells and the empty one, this is 3.2 bits per cell
+pub const PACKED_BOARD_LEN: usize = 20 * 10 / 5 * 2;
+// Digit of each cell, in order
+const PACKED_CELLS: [Option<Cell>; 9] = {
+    use crate::PieceId;
+    [
+        None,
+        Some(Cell::Piece(PieceId::I)),
+        Some(Cell::Piece(PieceId::O)),
+        Some(Cell::Piece(PieceId::T)),
+        Some(Cell::Piece(PieceId::S)),
+        Some(Cell::Piece(PieceId::Z)),
+        Some(Cell::Piece(PieceId::L)),
+        Some(Cell::Piece(PieceId::J)),
+        Some(Cell::Garbage),
+    ]
+};
+
 impl Board {
-    pub fn get_at(&self, position: &crate::Position) -> Option<&Option<crate::PieceId>> {
+    pub fn get_at(&self, position: &crate::Position) -> Option<&Option<Cell>> {
         let x_index = position.x().checked_sub(1)?;
         let y_index = position.y().checked_sub(1)?;
 
@@ -12,7 +54,7 @@ impl Board {
             .get(y_index as usize)
             .and_then(|row| row.get(x_index as usize))
     }
-    pub fn get_mut_at(&mut self, position: &crate::Position) -> Option<&mut Option<crate::PieceId>> {
+    pub fn get_mut_at(&mut self, position: &crate::Position) -> Option<&mut Option<Cell>> {
         let x_index = position.x().checked_sub(1)?;
         let y_index = position.y().checked_sub(1)?;
 
@@ -37,7 +79,7 @@ impl Board {
                 .0
                 .get_mut(p.y() as usize)
                 .and_then(|row| row.get_mut(p.x() as usize))
-                .unwrap() = Some(piece.id());
+                .unwrap() = Some(Cell::Piece(piece.id()));
         }
 
         Ok(())
@@ -63,6 +105,166 @@ impl Board {
         }
         true
     }
+
+    // Where the piece lands if hard dropped from the given position, used for the ghost piece
+    pub fn drop_position(
+        &self,
+        piece: &crate::Piece,
+        position: &crate::Position,
+    ) -> crate::Position {
+        let mut position = *position;
+
+        while let Some(below) = position
+            .offset(0, 1)
+            .filter(|below| self.can_place_at(piece, below))
+        {
+            position = below;
+        }
+
+        position
+    }
+
+    // Rotates following the SRS, the piece is moved by the first kick offset that lets it fit
+    //
+    // None if none of them do
+    pub fn rotate_piece(
+        &self,
+        piece: &crate::Piece,
+        position: &crate::Position,
+        rotation: crate::Rotation,
+    ) -> Option<(crate::Piece, crate::Position, (i8, i8))> {
+        let mut rotated = piece.clone();
+        rotated.rotate(rotation);
+
+        // The kick that worked is returned too, T-spin detection needs it
+        piece.kicks(rotation).iter().find_map(|kick| {
+            position
+                .offset(kick.0, kick.1)
+                .filter(|position| self.can_place_at(&rotated, position))
+                .map(|position| (rotated.clone(), position, *kick))
+        })
+    }
+
+    // Filled, or outside of the board
+    pub fn is_occupied(&self, position: &crate::Position, dx: i8, dy: i8) -> bool {
+        position.offset(dx, dy).is_none_or(|p| {
+            self.0
+                .get(p.y() as usize)
+                .and_then(|row| row.get(p.x() as usize))
+                .is_none_or(|cell| cell.is_some())
+        })
+    }
+
+    // Bit n is set if the cell at column n is filled
+    pub fn row_mask(&self, y: usize) -> Option<u16> {
+        let row = self.0.get(y)?;
+
+        Some(
+            row.iter()
+                .enumerate()
+                .filter(|(_, cell)| cell.is_some())
+                .fold(0, |mask, (x, _)| mask | 1 << x),
+        )
+    }
+
+    pub fn full_rows(&self) -> Vec<usize> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.iter().all(Option::is_some))
+            .map(|(y, _)| y)
+            .collect()
+    }
+
+    // Removes the given rows, every row above them falls down to fill the gap
+    pub fn remove_rows(&mut self, rows: &[usize]) {
+        let mut kept = self
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(y, _)| !rows.contains(y))
+            .map(|(_, row)| *row)
+            .collect::<Vec<BoardRow>>();
+
+        // Fill the top with empty rows
+        let mut new_rows = vec![BoardRow::default(); self.0.len() - kept.len()];
+        new_rows.append(&mut kept);
+        self.0.copy_from_slice(&new_rows);
+    }
+
+    // FNV-1a over every cell, for comparing boards without sending them around
+    // Hand rolled so it's the same on every target and compiler version
+    pub fn checksum(&self) -> u64 {
+        self.0
+            .iter()
+            .flatten()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, cell| {
+                let value = match cell {
+                    None => 0,
+                    Some(Cell::Piece(id)) => *id as u64 + 1,
+                    Some(Cell::Garbage) => 8,
+                };
+                (hash ^ value).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    // Pushes the stack up and fills the bottom rows, leaving the `hole` column empty
+    // Returns false if filled cells got pushed out of the top of the board
+    pub fn add_garbage(&mut self, lines: usize, hole: usize) -> bool {
+        let lines = lines.min(self.0.len());
+        let overflow = self.0[..lines]
+            .iter()
+            .any(|row| row.iter().any(|cell| cell.is_some()));
+
+        self.0.rotate_left(lines);
+
+        let height = self.0.len();
+        for row in &mut self.0[height - lines..] {
+            *row = std::array::from_fn(|x| (x != hole).then_some(Cell::Garbage));
+        }
+
+        !overflow
+    }
+
+    // Places the piece, then clears the rows it completed
+    //
+    // The returned masks are the cleared rows (top to bottom) as they were before the piece was placed,
+    // this is the shape the other players receive in ClientMessage::LinesDestroyed
+    // `last_kick` is the kick of the last rotation, if the piece has not moved since, for T-spins
+    pub fn place_and_clear(
+        &mut self,
+        piece: &crate::Piece,
+        position: &crate::Position,
+        last_kick: Option<(i8, i8)>,
+    ) -> Result<crate::LockResult, String> {
+        self.place_at(piece, position)?;
+
+        let rows = self.full_rows();
+        let kind = match last_kick {
+            Some(kick) => crate::clear::t_spin(self, piece, position, kick, rows.len() as u8),
+            None => crate::ClearKind::Lines(rows.len() as u8),
+        };
+
+        let masks = rows
+            .iter()
+            .map(|y| {
+                piece
+                    .bits()
+                    .iter()
+                    .map(|bit| bit + position)
+                    .filter(|bit| bit.y() as usize == *y)
+                    .fold(self.row_mask(*y).unwrap(), |mask, bit| mask & !(1 << bit.x()))
+            })
+            .collect::<Vec<u16>>();
+
+        self.remove_rows(&rows);
+
+        Ok(crate::LockResult {
+            perfect_clear: !masks.is_empty() && self.iter().all(|(cell, _)| cell.is_none()),
+            lines: masks,
+            kind,
+        })
+    }
 }
 
 pub struct BoardIterator<'a> {
@@ -72,9 +274,9 @@ pub struct BoardIterator<'a> {
 }
 
 impl<'a> Iterator for BoardIterator<'a> {
-    type Item = (Option<crate::PieceId>, crate::Position);
+    type Item = (Option<Cell>, crate::Position);
 
-    //   Some(Some(piece_id)) if the cell contains a piece.
+    //   Some(Some(cell)) if the cell is filled.
     //   Some(None) if the cell is empty.
     //   None when there are no more cells to iterate over.
 
@@ -104,7 +306,43 @@ impl<'a> Iterator for BoardIterator<'a> {
 }
 
 impl Board {
-    pub fn iter(&self) -> BoardIterator {
+    pub fn pack(&self) -> Vec<u8> {
+        self.0
+            .as_flattened()
+            .chunks(5)
+            .flat_map(|cells| {
+                cells.iter().rev().fold(0u16, |packed, cell| {
+                    let digit = PACKED_CELLS.iter().position(|packed_cell| packed_cell == cell).unwrap();
+                    packed * 9 + digit as u16
+                })
+                .to_le_bytes()
+            })
+            .collect()
+    }
+
+    // None if the bytes don't come from Board::pack
+    pub fn unpack(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != PACKED_BOARD_LEN {
+            return None;
+        }
+
+        let mut board = Self::default();
+        for (cells, packed) in board.0.as_flattened_mut().chunks_mut(5).zip(bytes.chunks(2)) {
+            let mut packed = u16::from_le_bytes([packed[0], packed[1]]);
+            for cell in cells {
+                *cell = PACKED_CELLS[(packed % 9) as usize];
+                packed /= 9;
+            }
+            // 9^5 fits in a u16, what's left is garbage
+            if packed != 0 {
+                return None;
+            }
+        }
+
+        Some(board)
+    }
+
+    pub fn iter(&self) -> BoardIterator<'_> {
         BoardIterator {
             board: self,
             row: 0,
@@ -112,3 +350,38 @@ impl Board {
         }
     }
 }
+
+// Rows of cells for text formats like JSON, packed for binary ones
+impl serde::Serialize for Board {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serde::Serialize::serialize(&self.0, serializer)
+        } else {
+            serializer.serialize_bytes(&self.pack())
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Board {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PackedVisitor;
+
+        impl serde::de::Visitor<'_> for PackedVisitor {
+            type Value = Board;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(formatter, "{PACKED_BOARD_LEN} bytes of packed cells")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Board, E> {
+                Board::unpack(bytes).ok_or_else(|| E::invalid_value(serde::de::Unexpected::Bytes(bytes), &self))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            <[BoardRow; 20] as serde::Deserialize>::deserialize(deserializer).map(Self)
+        } else {
+            deserializer.deserialize_bytes(PackedVisitor)
+        }
+    }
+}
@@ -0,0 +1,80 @@
+// What a lock did, as far as scoring and garbage are concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ClearKind {
+    // Plain clear, from 0 to 4 lines
+    Lines(u8),
+    // T-spins, 0 lines still counts
+    TSpinMini(u8),
+    TSpin(u8),
+}
+
+impl ClearKind {
+    pub fn lines(&self) -> u8 {
+        match self {
+            Self::Lines(lines) | Self::TSpinMini(lines) | Self::TSpin(lines) => *lines,
+        }
+    }
+
+    // Clears that keep the back to back going, tetrises and T-spins that clear something
+    pub fn is_difficult(&self) -> bool {
+        match self {
+            Self::Lines(lines) => *lines >= 4,
+            Self::TSpinMini(lines) | Self::TSpin(lines) => *lines > 0,
+        }
+    }
+}
+
+impl Default for ClearKind {
+    fn default() -> Self {
+        Self::Lines(0)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LockResult {
+    // Masks of the cleared rows, see Board::place_and_clear
+    pub lines: Vec<u16>,
+    pub kind: ClearKind,
+    // Nothing is left on the board
+    pub perfect_clear: bool,
+}
+
+// Kicks of 1 column and 2 rows turn a mini into a full T-spin, that's how a T-spin triple gets in
+fn is_tst_kick((dx, dy): (i8, i8)) -> bool {
+    dx.abs() == 1 && dy.abs() == 2
+}
+
+// 3-corner rule, for a T whose last successful move was a rotation using `kick`
+// Has to run before the cleared rows are removed, they hold some of the corners
+pub(crate) fn t_spin(
+    board: &crate::Board,
+    piece: &crate::Piece,
+    position: &crate::Position,
+    kick: (i8, i8),
+    lines: u8,
+) -> ClearKind {
+    use crate::Orientation::*;
+
+    if piece.id() != crate::PieceId::T {
+        return ClearKind::Lines(lines);
+    }
+
+    // Relative to the center of the T, the two corners it points to come first
+    let corners: [(i8, i8); 4] = match piece.orientation() {
+        Zero => [(-1, -1), (1, -1), (-1, 1), (1, 1)],
+        Right => [(1, -1), (1, 1), (-1, -1), (-1, 1)],
+        Two => [(-1, 1), (1, 1), (-1, -1), (1, -1)],
+        Left => [(-1, -1), (-1, 1), (1, -1), (1, 1)],
+    };
+    let occupied = corners.map(|(dx, dy)| board.is_occupied(position, dx, dy));
+
+    if occupied.iter().filter(|occupied| **occupied).count() < 3 {
+        return ClearKind::Lines(lines);
+    }
+
+    if (occupied[0] && occupied[1]) || is_tst_kick(kick) {
+        ClearKind::TSpin(lines)
+    } else {
+        ClearKind::TSpinMini(lines)
+    }
+}
@@ -0,0 +1,470 @@
+use std::{collections::VecDeque, time::Duration};
+
+// Where new pieces appear, roughly centered at the top of the board
+const SPAWN_POSITION: (u8, u8) = (4, 1);
+// Most garbage lines that can enter the board on a single lock, the rest waits for the next one
+const GARBAGE_CAP: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Input {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    Rotate(crate::Rotation),
+    Hold,
+}
+
+// Everything that can change a game, replaying the steps of a game on a new one built with the same config
+// and randomizer gives the same game, that's how the server checks the clients
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Step {
+    Input(Input),
+    Update(Duration),
+    Garbage { lines: u32, hole: u8 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    // The active piece has been written into the board
+    Locked(crate::PieceId),
+    // Masks of the cleared rows, see Board::place_and_clear
+    LinesCleared(Vec<u16>),
+    // Lines to send to the opponents, what's left after cancelling the incoming garbage
+    Attack(u32),
+    // The next piece could not spawn
+    GameOver,
+    // Config::line_goal lines got cleared
+    Finished,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Playing,
+    Over,
+    // Reached the line goal
+    Finished,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Config {
+    // Level the game starts at, it sets how fast pieces fall, see score::gravity
+    pub level: u32,
+    // How many upcoming pieces are visible
+    pub preview: usize,
+    // How long a piece can rest on the stack before it locks
+    pub lock_delay: Duration,
+    // How many times moving or rotating on the stack restarts the lock delay,
+    // given back every time the piece reaches a row lower than it has ever been
+    pub lock_resets: u32,
+    // Time received garbage waits before it can enter the board
+    pub garbage_delay: Duration,
+    // Lines to clear to finish the game, 40 for a sprint, None plays until topping out
+    #[serde(default)]
+    pub line_goal: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            level: 1,
+            preview: 5,
+            lock_delay: Duration::from_millis(500),
+            lock_resets: 15,
+            garbage_delay: Duration::from_millis(500),
+            line_goal: None,
+        }
+    }
+}
+
+// The rules of the game, driven by explicit inputs and time deltas so every side (front, server, bots)
+// runs the exact same simulation
+#[derive(Debug)]
+pub struct Game {
+    config: Config,
+    randomizer: Box<dyn crate::Randomizer + Send>,
+    queue: VecDeque<crate::PieceId>,
+    board: crate::Board,
+    piece: crate::Piece,
+    position: crate::Position,
+    hold: Option<crate::PieceId>,
+    // Only one hold per piece, reset when a piece locks
+    can_hold: bool,
+    state: State,
+    score: crate::Score,
+    gravity_timer: Duration,
+    // Time spent resting on the stack
+    lock_timer: Duration,
+    // Lock delay resets used since the piece reached its lowest row
+    resets: u32,
+    lowest_y: u8,
+    // Kick of the last rotation, cleared by any other move, locking with it set can be a T-spin
+    last_kick: Option<(i8, i8)>,
+    // Oldest first
+    garbage: VecDeque<crate::Garbage>,
+    // Every step since the last drain
+    steps: Vec<Step>,
+    events: Vec<Event>,
+}
+
+impl Game {
+    // Games built with the same randomizer and seed get the same pieces
+    pub fn new(config: Config, randomizer: impl crate::Randomizer + Send + 'static) -> Self {
+        let mut randomizer = Box::new(randomizer);
+        let id = randomizer.next();
+        let queue = (0..config.preview).map(|_| randomizer.next()).collect();
+
+        let mut game = Self {
+            score: crate::Score::new(config.level),
+            config,
+            randomizer,
+            queue,
+            board: crate::Board::default(),
+            piece: crate::Piece::from(id),
+            position: crate::Position::from(SPAWN_POSITION),
+            hold: None,
+            can_hold: true,
+            state: State::Playing,
+            gravity_timer: Duration::ZERO,
+            lock_timer: Duration::ZERO,
+            resets: 0,
+            lowest_y: SPAWN_POSITION.1,
+            last_kick: None,
+            garbage: VecDeque::new(),
+            steps: Vec::new(),
+            events: Vec::new(),
+        };
+        game.spawn(id);
+        game
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+    pub fn board(&self) -> &crate::Board {
+        &self.board
+    }
+    pub fn piece(&self) -> &crate::Piece {
+        &self.piece
+    }
+    pub fn position(&self) -> crate::Position {
+        self.position
+    }
+    // Where the active piece would land if hard dropped
+    pub fn ghost_position(&self) -> crate::Position {
+        self.board.drop_position(&self.piece, &self.position)
+    }
+    // Upcoming pieces, the first one is next
+    pub fn queue(&self) -> &VecDeque<crate::PieceId> {
+        &self.queue
+    }
+    pub fn hold(&self) -> Option<crate::PieceId> {
+        self.hold
+    }
+    pub fn can_hold(&self) -> bool {
+        self.can_hold
+    }
+    pub fn state(&self) -> State {
+        self.state
+    }
+    // Topped out or finished, nothing moves anymore
+    pub fn is_over(&self) -> bool {
+        self.state != State::Playing
+    }
+    pub fn score(&self) -> &crate::Score {
+        &self.score
+    }
+    // Incoming garbage, oldest first
+    pub fn garbage(&self) -> &VecDeque<crate::Garbage> {
+        &self.garbage
+    }
+    // Time it takes for the active piece to fall one row on its own at the current level
+    pub fn gravity(&self) -> Duration {
+        crate::gravity(self.score.level())
+    }
+
+    // Everything that happened since the last call
+    pub fn drain_events(&mut self) -> std::vec::Drain<'_, Event> {
+        self.events.drain(..)
+    }
+
+    // Every step since the last call, the game records them whoever drives it
+    pub fn drain_steps(&mut self) -> std::vec::Drain<'_, Step> {
+        self.steps.drain(..)
+    }
+
+    pub fn step(&mut self, step: Step) {
+        match step {
+            Step::Input(input) => self.input(input),
+            Step::Update(delta) => self.update(delta),
+            Step::Garbage { lines, hole } => self.receive_garbage(lines, hole),
+        }
+    }
+
+    pub fn input(&mut self, input: Input) {
+        self.steps.push(Step::Input(input));
+
+        if self.is_over() {
+            return;
+        }
+
+        match input {
+            Input::MoveLeft | Input::MoveRight => {
+                let grounded = self.grounded();
+                let dx = if input == Input::MoveLeft { -1 } else { 1 };
+
+                if self.try_move(dx, 0) {
+                    self.lock_reset(grounded);
+                }
+            }
+            Input::SoftDrop => {
+                if self.fall() {
+                    self.gravity_timer = Duration::ZERO;
+                    self.score.soft_drop(1);
+                }
+            }
+            Input::HardDrop => {
+                let ghost = self.ghost_position();
+                if ghost.y() != self.position.y() {
+                    // Dropping is a move, no T-spin after that
+                    self.last_kick = None;
+                }
+
+                self.score.hard_drop((ghost.y() - self.position.y()) as u32);
+                self.position = ghost;
+                self.lock();
+            }
+            Input::Rotate(rotation) => {
+                let grounded = self.grounded();
+
+                if let Some((piece, position, kick)) = self.board.rotate_piece(&self.piece, &self.position, rotation) {
+                    self.piece = piece;
+                    self.position = position;
+                    self.last_kick = Some(kick);
+                    self.lock_reset(grounded);
+                }
+            }
+            Input::Hold => {
+                if !self.can_hold {
+                    return;
+                }
+
+                // The held piece comes back in its spawn state, at the spawn position
+                let id = match self.hold.replace(self.piece.id()) {
+                    Some(held) => held,
+                    None => self.next_piece(),
+                };
+
+                self.spawn(id);
+                self.can_hold = false;
+            }
+        }
+    }
+
+    // Garbage sent by an opponent, it enters the board after the configured delay unless it gets cancelled first
+    pub fn receive_garbage(&mut self, lines: u32, hole: u8) {
+        self.steps.push(Step::Garbage { lines, hole });
+
+        if self.is_over() || lines == 0 {
+            return;
+        }
+
+        self.garbage.push_back(crate::Garbage {
+            lines,
+            // Boards are 10 wide
+            hole: hole.min(9),
+            delay: self.config.garbage_delay,
+        });
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        self.steps.push(Step::Update(delta));
+
+        if self.is_over() {
+            return;
+        }
+
+        for garbage in self.garbage.iter_mut() {
+            garbage.delay = garbage.delay.saturating_sub(delta);
+        }
+
+        let mut remaining = delta;
+
+        // Large deltas play out step by step, falling and locking as many times as they contain
+        while !self.is_over() {
+            if self.grounded() {
+                let left = self.config.lock_delay.saturating_sub(self.lock_timer);
+                if remaining < left {
+                    self.lock_timer += remaining;
+                    break;
+                }
+
+                remaining -= left;
+                self.lock();
+            } else {
+                let left = self.gravity().saturating_sub(self.gravity_timer);
+                if remaining < left {
+                    self.gravity_timer += remaining;
+                    break;
+                }
+
+                remaining -= left;
+                self.gravity_timer = Duration::ZERO;
+                self.fall();
+            }
+        }
+    }
+
+    // Resting on the stack or the floor
+    fn grounded(&self) -> bool {
+        !self
+            .position
+            .offset(0, 1)
+            .is_some_and(|position| self.board.can_place_at(&self.piece, &position))
+    }
+
+    fn fall(&mut self) -> bool {
+        if !self.try_move(0, 1) {
+            return false;
+        }
+
+        self.lock_timer = Duration::ZERO;
+        if self.position.y() > self.lowest_y {
+            self.lowest_y = self.position.y();
+            self.last_kick = None;
+            self.resets = 0;
+        }
+        true
+    }
+
+    // Moving or rotating on the stack buys the piece some time, but only so many times
+    fn lock_reset(&mut self, grounded: bool) {
+        if grounded && self.resets < self.config.lock_resets {
+            self.resets += 1;
+            self.lock_timer = Duration::ZERO;
+        }
+    }
+
+    fn try_move(&mut self, dx: i8, dy: i8) -> bool {
+        let Some(position) = self.position.offset(dx, dy) else {
+            return false;
+        };
+
+        if !self.board.can_place_at(&self.piece, &position) {
+            return false;
+        }
+
+        self.position = position;
+        self.last_kick = None;
+        true
+    }
+
+    fn lock(&mut self) {
+        // The active piece is only ever moved to valid spots, so this cannot fail
+        let result = self.board.place_and_clear(&self.piece, &self.position, self.last_kick).unwrap();
+        self.events.push(Event::Locked(self.piece.id()));
+
+        let back_to_back = self.score.back_to_back();
+        self.score.lock(&result);
+
+        let attack = crate::attack(
+            result.kind,
+            self.score.combo(),
+            back_to_back,
+            result.perfect_clear,
+        );
+        let attack = self.cancel_garbage(attack);
+        if attack > 0 {
+            self.events.push(Event::Attack(attack));
+        }
+
+        if !result.lines.is_empty() {
+            self.events.push(Event::LinesCleared(result.lines));
+
+            if self.config.line_goal.is_some_and(|goal| self.score.lines() >= goal) {
+                self.state = State::Finished;
+                self.events.push(Event::Finished);
+                return;
+            }
+        } else if !self.insert_garbage() {
+            self.state = State::Over;
+            self.events.push(Event::GameOver);
+            return;
+        }
+
+        let id = self.next_piece();
+        self.spawn(id);
+        self.can_hold = true;
+    }
+
+    // Attacks first cancel the incoming garbage, oldest first, returns what is left to send
+    fn cancel_garbage(&mut self, mut attack: u32) -> u32 {
+        while attack > 0 {
+            let Some(garbage) = self.garbage.front_mut() else {
+                break;
+            };
+
+            let cancelled = attack.min(garbage.lines);
+            attack -= cancelled;
+            garbage.lines -= cancelled;
+
+            if garbage.lines == 0 {
+                self.garbage.pop_front();
+            }
+        }
+
+        attack
+    }
+
+    // Ready garbage enters the board when a piece locks without clearing anything
+    // Returns false if it pushed the stack out of the board
+    fn insert_garbage(&mut self) -> bool {
+        let mut left = GARBAGE_CAP;
+
+        while left > 0 {
+            let Some(garbage) = self
+                .garbage
+                .front_mut()
+                .filter(|garbage| garbage.is_ready())
+            else {
+                break;
+            };
+
+            let lines = left.min(garbage.lines);
+            left -= lines;
+            garbage.lines -= lines;
+
+            let hole = garbage.hole as usize;
+            if garbage.lines == 0 {
+                self.garbage.pop_front();
+            }
+
+            if !self.board.add_garbage(lines as usize, hole) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // Takes the first piece of the queue, the sequence is the same whatever the preview length
+    fn next_piece(&mut self) -> crate::PieceId {
+        self.queue.push_back(self.randomizer.next());
+        self.queue.pop_front().unwrap()
+    }
+
+    fn spawn(&mut self, id: crate::PieceId) {
+        self.piece = crate::Piece::from(id);
+        self.position = crate::Position::from(SPAWN_POSITION);
+        self.gravity_timer = Duration::ZERO;
+        self.lock_timer = Duration::ZERO;
+        self.resets = 0;
+        self.lowest_y = self.position.y();
+        self.last_kick = None;
+
+        if !self.board.can_place_at(&self.piece, &self.position) {
+            self.state = State::Over;
+            self.events.push(Event::GameOver);
+        }
+    }
+}
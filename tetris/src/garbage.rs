@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+// Lines sent by combo count, the first clear of a chain is combo 0, longer combos keep the last value
+const COMBO_TABLE: [u32; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+
+const BACK_TO_BACK_BONUS: u32 = 1;
+const PERFECT_CLEAR_BONUS: u32 = 10;
+
+// Lines sent to the opponents for a lock, `back_to_back` is whether the previous clear was difficult
+pub fn attack(
+    kind: crate::ClearKind,
+    combo: Option<u32>,
+    back_to_back: bool,
+    perfect_clear: bool,
+) -> u32 {
+    use crate::ClearKind::*;
+
+    let lines = match kind {
+        Lines(0..=1) => 0,
+        Lines(2) => 1,
+        Lines(3) => 2,
+        Lines(_) => 4,
+        TSpinMini(0..=1) => 0,
+        TSpinMini(_) => 1,
+        TSpin(0) => 0,
+        TSpin(1) => 2,
+        TSpin(2) => 4,
+        TSpin(_) => 6,
+    };
+
+    // Nothing cleared, nothing sent
+    if kind.lines() == 0 {
+        return 0;
+    }
+
+    let back_to_back = if back_to_back && kind.is_difficult() {
+        BACK_TO_BACK_BONUS
+    } else {
+        0
+    };
+    let combo = combo.map_or(0, |combo| {
+        COMBO_TABLE[(combo as usize).min(COMBO_TABLE.len() - 1)]
+    });
+    let perfect_clear = if perfect_clear {
+        PERFECT_CLEAR_BONUS
+    } else {
+        0
+    };
+
+    lines + back_to_back + combo + perfect_clear
+}
+
+// Lines on their way to a player's board
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Garbage {
+    pub lines: u32,
+    // Column left empty in every one of these lines
+    pub hole: u8,
+    // Time left before it can enter the board, until then it can only be cancelled
+    pub delay: Duration,
+}
+
+impl Garbage {
+    pub fn is_ready(&self) -> bool {
+        self.delay.is_zero()
+    }
+}
@@ -1,9 +1,19 @@
 mod board;
+mod clear;
 mod color;
+mod game;
+mod garbage;
 mod piece;
 mod position;
+mod randomizer;
+mod score;
 
-pub use board::{Board, BoardRow};
+pub use board::{Board, BoardRow, Cell, PACKED_BOARD_LEN};
+pub use clear::{ClearKind, LockResult};
 pub use color::Color;
-pub use piece::{Piece, PieceId};
+pub use game::{Config, Event, Game, Input, State, Step};
+pub use garbage::{Garbage, attack};
+pub use piece::{Orientation, Piece, PieceId, Rotation};
 pub use position::Position;
+pub use randomizer::{History, Randomizer, SevenBag, Uniform};
+pub use score::{Score, gravity};
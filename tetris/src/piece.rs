@@ -10,6 +10,16 @@ pub enum PieceId {
 }
 
 impl PieceId {
+    pub const ALL: [PieceId; 7] = [
+        Self::I,
+        Self::O,
+        Self::T,
+        Self::S,
+        Self::Z,
+        Self::L,
+        Self::J,
+    ];
+
     pub fn color(&self) -> crate::Color {
         use crate::Color;
         match self {
@@ -22,17 +32,6 @@ impl PieceId {
             Self::J => Color::from((0, 0, 255)),
         }
     }
-    pub fn random() -> Self {
-        *random::pick(&[
-            Self::I,
-            Self::O,
-            Self::T,
-            Self::S,
-            Self::Z,
-            Self::L,
-            Self::J,
-        ])
-    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -55,11 +54,48 @@ impl std::ops::Add<&crate::Position> for &Bit {
     }
 }
 
+// Orientations as named by the SRS: 0 is the spawn state, R and L are one clockwise / counter-clockwise turn
+// away from it, and 2 is a half turn
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Orientation {
+    #[default]
+    Zero,
+    Right,
+    Two,
+    Left,
+}
+
+impl Orientation {
+    pub fn rotated(&self, rotation: Rotation) -> Self {
+        use Orientation::*;
+
+        const ORDER: [Orientation; 4] = [Zero, Right, Two, Left];
+
+        let index = ORDER.iter().position(|o| o == self).unwrap();
+
+        let turns = match rotation {
+            Rotation::Clockwise => 1,
+            Rotation::Half => 2,
+            Rotation::CounterClockwise => 3,
+        };
+
+        ORDER[(index + turns) % ORDER.len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+    Half,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Piece {
     id: PieceId,
     bits: Vec<Bit>,
     color: crate::color::Color,
+    orientation: Orientation,
 }
 
 impl Piece {
@@ -72,59 +108,123 @@ impl Piece {
     pub fn color(&self) -> &super::Color {
         &self.color
     }
-    pub fn rotate(&mut self) {
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    // Rotates in place, without any kind of collision check, see Board::rotate_piece for that
+    pub fn rotate(&mut self, rotation: Rotation) {
+        if rotation == Rotation::Half {
+            self.rotate(Rotation::Clockwise);
+            self.rotate(Rotation::Clockwise);
+            return;
+        }
+
+        self.orientation = self.orientation.rotated(rotation);
+
+        // The O piece rotates around the center of its 2x2 box, so it ends up on the same cells
         if self.id == PieceId::O {
             return;
         }
 
+        // The y axis points down, and the I piece rotates around the bottom right corner of its (0, 0) cell
+        let shift = if self.id == PieceId::I { 1 } else { 0 };
+
         for bit in self.bits.iter_mut() {
-            let old_x = bit.0;
-            let old_y = bit.1;
-            bit.0 = old_y;
-            bit.1 = -old_x;
+            let Bit(x, y) = *bit;
+
+            *bit = match rotation {
+                Rotation::Clockwise => Bit(shift - y, x),
+                Rotation::CounterClockwise => Bit(y, shift - x),
+                Rotation::Half => unreachable!(),
+            };
+        }
+    }
+
+    // Offsets to try, in order, when rotating from the current orientation, the first one that fits wins
+    pub fn kicks(&self, rotation: Rotation) -> &'static [(i8, i8)] {
+        use Orientation::*;
+
+        // Tables from https://tetris.wiki/Super_Rotation_System, with the y axis flipped since it points down here
+        const NONE: &[(i8, i8)] = &[(0, 0)];
+
+        const JLSTZ_0_R: &[(i8, i8)] = &[(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)];
+        const JLSTZ_R_0: &[(i8, i8)] = &[(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)];
+        const JLSTZ_R_2: &[(i8, i8)] = &[(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)];
+        const JLSTZ_2_R: &[(i8, i8)] = &[(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)];
+        const JLSTZ_2_L: &[(i8, i8)] = &[(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)];
+        const JLSTZ_L_2: &[(i8, i8)] = &[(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)];
+        const JLSTZ_L_0: &[(i8, i8)] = &[(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)];
+        const JLSTZ_0_L: &[(i8, i8)] = &[(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)];
+
+        const I_0_R: &[(i8, i8)] = &[(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)];
+        const I_R_0: &[(i8, i8)] = &[(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)];
+        const I_R_2: &[(i8, i8)] = &[(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)];
+        const I_2_R: &[(i8, i8)] = &[(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)];
+        const I_2_L: &[(i8, i8)] = &[(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)];
+        const I_L_2: &[(i8, i8)] = &[(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)];
+        const I_L_0: &[(i8, i8)] = &[(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)];
+        const I_0_L: &[(i8, i8)] = &[(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)];
+
+        // The SRS has no half turns, these come from the SRS+ variant, and are shared by every piece
+        const HALF_0_2: &[(i8, i8)] = &[(0, 0), (0, -1), (1, -1), (-1, -1), (1, 0), (-1, 0)];
+        const HALF_2_0: &[(i8, i8)] = &[(0, 0), (0, 1), (-1, 1), (1, 1), (-1, 0), (1, 0)];
+        const HALF_R_L: &[(i8, i8)] = &[(0, 0), (1, 0), (1, -2), (1, -1), (0, -2), (0, -1)];
+        const HALF_L_R: &[(i8, i8)] = &[(0, 0), (-1, 0), (-1, -2), (-1, -1), (0, -2), (0, -1)];
+
+        let to = self.orientation.rotated(rotation);
+
+        match (self.id, self.orientation, to) {
+            (PieceId::O, _, _) => NONE,
+
+            (_, Zero, Two) => HALF_0_2,
+            (_, Two, Zero) => HALF_2_0,
+            (_, Right, Left) => HALF_R_L,
+            (_, Left, Right) => HALF_L_R,
+
+            (PieceId::I, Zero, Right) => I_0_R,
+            (PieceId::I, Right, Zero) => I_R_0,
+            (PieceId::I, Right, Two) => I_R_2,
+            (PieceId::I, Two, Right) => I_2_R,
+            (PieceId::I, Two, Left) => I_2_L,
+            (PieceId::I, Left, Two) => I_L_2,
+            (PieceId::I, Left, Zero) => I_L_0,
+            (PieceId::I, Zero, Left) => I_0_L,
+
+            (_, Zero, Right) => JLSTZ_0_R,
+            (_, Right, Zero) => JLSTZ_R_0,
+            (_, Right, Two) => JLSTZ_R_2,
+            (_, Two, Right) => JLSTZ_2_R,
+            (_, Two, Left) => JLSTZ_2_L,
+            (_, Left, Two) => JLSTZ_L_2,
+            (_, Left, Zero) => JLSTZ_L_0,
+            (_, Zero, Left) => JLSTZ_0_L,
+
+            // A rotation always changes the orientation
+            (_, from, to) => unreachable!("{from:?} -> {to:?}"),
         }
     }
 }
 
-// Using the 'super rotation system' from https://strategywiki.org/wiki/Tetris/Rotation_systems
+// Spawn states of the 'super rotation system' from https://tetris.wiki/Super_Rotation_System
+// The y axis points down, so negative y bits are above the piece's position
 impl From<PieceId> for Piece {
     fn from(id: PieceId) -> Self {
-        match id {
-            PieceId::I => Piece {
-                id,
-                bits: vec![Bit(-2, 0), Bit(-1, 0), Bit(0, 0), Bit(1, 0)],
-                color: id.color(),
-            },
-            PieceId::O => Piece {
-                id,
-                bits: vec![Bit(0, 0), Bit(0, 1), Bit(1, 0), Bit(1, 1)],
-                color: id.color(),
-            },
-            PieceId::J => Piece {
-                id,
-                bits: vec![Bit(-1, -1), Bit(-1, 0), Bit(0, 0), Bit(1, 0)],
-                color: id.color(),
-            },
-            PieceId::L => Piece {
-                id,
-                bits: vec![Bit(-1, 0), Bit(0, 0), Bit(1, -1), Bit(1, 0)],
-                color: id.color(),
-            },
-            PieceId::T => Piece {
-                id,
-                bits: vec![Bit(-1, 0), Bit(0, 0), Bit(1, 0), Bit(0, 1)],
-                color: id.color(),
-            },
-            PieceId::S => Piece {
-                id,
-                bits: vec![Bit(-1, 1), Bit(0, 0), Bit(0, 1), Bit(1, 0)],
-                color: id.color(),
-            },
-            PieceId::Z => Piece {
-                id,
-                bits: vec![Bit(-1, 0), Bit(0, 0), Bit(0, 1), Bit(1, 1)],
-                color: id.color(),
-            },
+        let bits = match id {
+            PieceId::I => vec![Bit(-1, 0), Bit(0, 0), Bit(1, 0), Bit(2, 0)],
+            PieceId::O => vec![Bit(0, -1), Bit(1, -1), Bit(0, 0), Bit(1, 0)],
+            PieceId::J => vec![Bit(-1, -1), Bit(-1, 0), Bit(0, 0), Bit(1, 0)],
+            PieceId::L => vec![Bit(1, -1), Bit(-1, 0), Bit(0, 0), Bit(1, 0)],
+            PieceId::T => vec![Bit(0, -1), Bit(-1, 0), Bit(0, 0), Bit(1, 0)],
+            PieceId::S => vec![Bit(0, -1), Bit(1, -1), Bit(-1, 0), Bit(0, 0)],
+            PieceId::Z => vec![Bit(-1, -1), Bit(0, -1), Bit(0, 0), Bit(1, 0)],
+        };
+
+        Piece {
+            id,
+            bits,
+            color: id.color(),
+            orientation: Orientation::default(),
         }
     }
 }
@@ -8,6 +8,14 @@ impl Position{
     pub fn y(&self) -> u8 {
         self.1
     }
+
+    // None if the resulting position would be negative or overflow
+    pub fn offset(&self, dx: i8, dy: i8) -> Option<Position> {
+        Some(Position(
+            self.0.checked_add_signed(dx)?,
+            self.1.checked_add_signed(dy)?,
+        ))
+    }
 }
 
 impl From<(u8, u8)> for Position{
@@ -0,0 +1,135 @@
+use crate::PieceId;
+
+// Source of the piece sequence, two randomizers of the same kind built with the same seed give the same pieces
+pub trait Randomizer: std::fmt::Debug {
+    fn next(&mut self) -> PieceId;
+}
+
+// SplitMix64, hand rolled so a seed gives the same sequence on every target (wasm front, native server, bots)
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // In 0..max, the modulo bias is negligible for the tiny ranges used here
+    pub(crate) fn below(&mut self, max: usize) -> usize {
+        (self.next_u64() % max as u64) as usize
+    }
+
+    pub(crate) fn pick<'a, T>(&mut self, values: &'a [T]) -> &'a T {
+        &values[self.below(values.len())]
+    }
+}
+
+// Every piece once, in a random order, then again with a new order
+// This is what the guideline uses, droughts can't be longer than 12 pieces
+#[derive(Debug, Clone)]
+pub struct SevenBag {
+    rng: Rng,
+    bag: Vec<PieceId>,
+}
+
+impl SevenBag {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            bag: Vec::with_capacity(PieceId::ALL.len()),
+        }
+    }
+}
+
+impl Randomizer for SevenBag {
+    fn next(&mut self) -> PieceId {
+        if self.bag.is_empty() {
+            self.bag.extend(PieceId::ALL);
+
+            // Fisher-Yates
+            for i in (1..self.bag.len()).rev() {
+                let j = self.rng.below(i + 1);
+                self.bag.swap(i, j);
+            }
+        }
+
+        self.bag.pop().unwrap()
+    }
+}
+
+// Every piece has the same chance every time, nothing prevents floods or droughts
+#[derive(Debug, Clone)]
+pub struct Uniform {
+    rng: Rng,
+}
+
+impl Uniform {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+        }
+    }
+}
+
+impl Randomizer for Uniform {
+    fn next(&mut self) -> PieceId {
+        *self.rng.pick(&PieceId::ALL)
+    }
+}
+
+// The TGM randomizer: remembers the last 4 pieces and re-rolls up to 4 times while the roll is one of them
+// The history starts full of Z, and the first piece is never S, Z or O
+#[derive(Debug, Clone)]
+pub struct History {
+    rng: Rng,
+    history: [PieceId; 4],
+    first: bool,
+}
+
+impl History {
+    const ROLLS: usize = 4;
+
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            history: [PieceId::Z; 4],
+            first: true,
+        }
+    }
+}
+
+impl Randomizer for History {
+    fn next(&mut self) -> PieceId {
+        let id = if self.first {
+            self.first = false;
+            *self
+                .rng
+                .pick(&[PieceId::I, PieceId::T, PieceId::L, PieceId::J])
+        } else {
+            let mut id = *self.rng.pick(&PieceId::ALL);
+
+            for _ in 1..Self::ROLLS {
+                if !self.history.contains(&id) {
+                    break;
+                }
+                id = *self.rng.pick(&PieceId::ALL);
+            }
+
+            id
+        };
+
+        self.history.rotate_left(1);
+        self.history[self.history.len() - 1] = id;
+
+        id
+    }
+}
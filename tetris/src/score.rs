@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+// Lines needed to go up a level
+const LINES_PER_LEVEL: u32 = 10;
+
+// Time per row for each level, from the guideline formula (0.8 - (level - 1) * 0.007) ^ (level - 1) seconds
+// Stored instead of computed so floats can't make two targets disagree
+const GRAVITY_MICROS: [u64; 20] = [
+    1_000_000, 793_000, 617_796, 472_729, 355_197, 262_004, 189_677, 134_735, 93_882, 64_152,
+    42_976, 28_218, 18_153, 11_439, 7_059, 4_264, 2_520, 1_457, 824, 455,
+];
+
+// How long it takes a piece to fall one row at the given level, levels past the table keep the last speed
+pub fn gravity(level: u32) -> Duration {
+    let index = (level.max(1) as usize - 1).min(GRAVITY_MICROS.len() - 1);
+    Duration::from_micros(GRAVITY_MICROS[index])
+}
+
+// Guideline scoring, with points multiplied by the level the clear happened at
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Score {
+    points: u64,
+    start_level: u32,
+    lines: u32,
+    // Clears in a row, the first one of a chain is combo 0
+    combo: Option<u32>,
+    // The last clear was difficult (a tetris or a T-spin), the next one gets a bonus if it is too
+    back_to_back: bool,
+}
+
+impl Score {
+    pub fn new(start_level: u32) -> Self {
+        Self {
+            points: 0,
+            start_level: start_level.max(1),
+            lines: 0,
+            combo: None,
+            back_to_back: false,
+        }
+    }
+
+    pub fn points(&self) -> u64 {
+        self.points
+    }
+    pub fn level(&self) -> u32 {
+        self.start_level + self.lines / LINES_PER_LEVEL
+    }
+    pub fn lines(&self) -> u32 {
+        self.lines
+    }
+    pub fn combo(&self) -> Option<u32> {
+        self.combo
+    }
+    pub fn back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    pub fn soft_drop(&mut self, rows: u32) {
+        self.points += rows as u64;
+    }
+
+    pub fn hard_drop(&mut self, rows: u32) {
+        self.points += 2 * rows as u64;
+    }
+
+    // Called on every lock, even without lines, since that is what breaks combos
+    // Returns the points the lock was worth
+    pub fn lock(&mut self, result: &crate::LockResult) -> u64 {
+        use crate::ClearKind::*;
+
+        let level = self.level() as u64;
+        let lines = result.kind.lines() as u32;
+        let difficult = result.kind.is_difficult();
+        let back_to_back = difficult && self.back_to_back;
+
+        let mut points = match result.kind {
+            Lines(0) => 0,
+            Lines(1) => 100,
+            Lines(2) => 300,
+            Lines(3) => 500,
+            Lines(_) => 800,
+            TSpinMini(0) => 100,
+            TSpinMini(1) => 200,
+            TSpinMini(_) => 400,
+            TSpin(0) => 400,
+            TSpin(1) => 800,
+            TSpin(2) => 1200,
+            TSpin(_) => 1600,
+        } * level;
+
+        // T-spins without lines still score, but they are not clears
+        if lines == 0 {
+            self.points += points;
+            self.combo = None;
+            return points;
+        }
+
+        if back_to_back {
+            points += points / 2;
+        }
+
+        let combo = self.combo.map_or(0, |combo| combo + 1);
+        points += 50 * combo as u64 * level;
+
+        if result.perfect_clear {
+            points += match (lines, back_to_back) {
+                (1, _) => 800,
+                (2, _) => 1200,
+                (3, _) => 1800,
+                (_, false) => 2000,
+                (_, true) => 3200,
+            } * level;
+        }
+
+        self.points += points;
+        self.lines += lines;
+        self.combo = Some(combo);
+        self.back_to_back = difficult;
+
+        points
+    }
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
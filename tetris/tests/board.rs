@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod tests {
+    use tetris::*;
+
+    // Fills the given (0 indexed) row, except for the columns in `holes`
+    fn fill_row(board: &mut Board, y: u8, holes: &[u8]) {
+        for x in (0..10).filter(|x| !holes.contains(x)) {
+            *board.get_mut_at(&Position::from((x + 1, y + 1))).unwrap() = Some(Cell::Piece(PieceId::O));
+        }
+    }
+
+    // Position that puts the leftmost bit of a flat I in column 0 of the given row
+    fn flat_i_at(y: u8) -> (Piece, Position) {
+        let piece = Piece::from(PieceId::I);
+        let min_x = piece.bits().iter().map(|bit| bit.x()).min().unwrap();
+        let min_y = piece.bits().iter().map(|bit| bit.y()).min().unwrap();
+
+        (piece, Position::from(((-min_x) as u8, (y as i8 - min_y) as u8)))
+    }
+
+    #[test]
+    fn row_mask() {
+        let mut board = Board::default();
+        fill_row(&mut board, 19, &[0, 9]);
+
+        assert_eq!(board.row_mask(19), Some(0b0111111110));
+        assert_eq!(board.row_mask(18), Some(0));
+        assert_eq!(board.row_mask(20), None);
+    }
+
+    #[test]
+    fn full_rows() {
+        let mut board = Board::default();
+        fill_row(&mut board, 19, &[]);
+        fill_row(&mut board, 18, &[3]);
+        fill_row(&mut board, 17, &[]);
+
+        assert_eq!(board.full_rows(), vec![17, 19]);
+    }
+
+    #[test]
+    fn remove_rows_collapses() {
+        let mut board = Board::default();
+        fill_row(&mut board, 19, &[]);
+        fill_row(&mut board, 18, &[3]);
+        fill_row(&mut board, 17, &[]);
+        fill_row(&mut board, 16, &[5, 6]);
+
+        board.remove_rows(&board.full_rows());
+
+        assert_eq!(board.full_rows(), Vec::<usize>::new());
+        assert_eq!(board.row_mask(19), Some(0b1111110111));
+        assert_eq!(board.row_mask(18), Some(0b1110011111));
+        assert_eq!(board.row_mask(17), Some(0));
+        assert_eq!(board.row_mask(0), Some(0));
+    }
+
+    #[test]
+    fn place_and_clear() {
+        let mut board = Board::default();
+        fill_row(&mut board, 19, &[0, 1, 2, 3]);
+        fill_row(&mut board, 18, &[0, 1, 2, 3, 4]);
+
+        let (piece, position) = flat_i_at(19);
+        let lines = board.place_and_clear(&piece, &position, None).unwrap().lines;
+
+        // The piece itself is not part of the reported shape
+        assert_eq!(lines, vec![0b1111110000]);
+        assert_eq!(board.row_mask(19), Some(0b1111100000));
+        assert_eq!(board.row_mask(18), Some(0));
+    }
+
+    #[test]
+    fn place_without_clear() {
+        let mut board = Board::default();
+        fill_row(&mut board, 19, &[0, 1, 2, 3, 4]);
+
+        let (piece, position) = flat_i_at(19);
+        let lines = board.place_and_clear(&piece, &position, None).unwrap().lines;
+
+        assert!(lines.is_empty());
+        assert_eq!(board.row_mask(19), Some(0b1111101111));
+    }
+
+    #[test]
+    fn perfect_clear() {
+        let mut board = Board::default();
+        fill_row(&mut board, 19, &[0, 1, 2, 3]);
+
+        let (piece, position) = flat_i_at(19);
+        let result = board.place_and_clear(&piece, &position, None).unwrap();
+
+        assert!(result.perfect_clear);
+        assert_eq!(result.kind, ClearKind::Lines(1));
+    }
+
+    #[test]
+    fn drop_position() {
+        let mut board = Board::default();
+        let (piece, position) = flat_i_at(2);
+
+        // Floor
+        assert_eq!(board.drop_position(&piece, &position).y(), 19);
+
+        // Stack, with a hole the piece can't reach
+        fill_row(&mut board, 15, &[0, 1, 2]);
+        fill_row(&mut board, 17, &[]);
+        assert_eq!(board.drop_position(&piece, &position).y(), 14);
+
+        // Already resting
+        let resting = Position::from((position.x(), 14));
+        assert_eq!(board.drop_position(&piece, &resting), resting);
+    }
+
+    #[test]
+    fn packing() {
+        let mut board = Board::default();
+        fill_row(&mut board, 0, &[3]);
+        *board.get_mut_at(&Position::from((4, 2))).unwrap() = Some(Cell::Garbage);
+        *board.get_mut_at(&Position::from((10, 20))).unwrap() = Some(Cell::Piece(PieceId::J));
+
+        let packed = board.pack();
+        assert_eq!(packed.len(), PACKED_BOARD_LEN);
+        assert_eq!(Board::unpack(&packed), Some(board));
+
+        assert_eq!(Board::unpack(&packed[1..]), None);
+        // Above 9^5
+        let mut invalid = packed.clone();
+        invalid[0] = 0xff;
+        invalid[1] = 0xff;
+        assert_eq!(Board::unpack(&invalid), None);
+    }
+}
@@ -0,0 +1,302 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use tetris::*;
+
+    fn new_game() -> Game {
+        Game::new(Config::default(), SevenBag::new(0))
+    }
+
+    #[test]
+    fn gravity() {
+        let mut game = new_game();
+        let start = game.position();
+
+        game.update(game.gravity() / 2);
+        assert_eq!(game.position(), start);
+
+        game.update(game.gravity() / 2);
+        assert_eq!(game.position().y(), start.y() + 1);
+
+        // Large deltas apply every row they contain
+        game.update(game.gravity() * 3);
+        assert_eq!(game.position().y(), start.y() + 4);
+    }
+
+    #[test]
+    fn moves() {
+        let mut game = new_game();
+        let start = game.position();
+
+        game.input(Input::MoveLeft);
+        assert_eq!(game.position().x(), start.x() - 1);
+
+        game.input(Input::MoveRight);
+        game.input(Input::MoveRight);
+        assert_eq!(game.position().x(), start.x() + 1);
+
+        game.input(Input::SoftDrop);
+        assert_eq!(game.position().y(), start.y() + 1);
+
+        // Walls stop the piece
+        for _ in 0..20 {
+            game.input(Input::MoveLeft);
+        }
+        assert!(game.position().x() <= 2);
+        assert!(game.drain_events().next().is_none());
+    }
+
+    #[test]
+    fn hard_drop_locks() {
+        let mut game = new_game();
+        let id = game.piece().id();
+
+        game.input(Input::HardDrop);
+
+        assert_eq!(game.drain_events().collect::<Vec<_>>(), vec![Event::Locked(id)]);
+        assert_eq!(game.board().iter().filter(|(cell, _)| cell.is_some()).count(), 4);
+    }
+
+    #[test]
+    fn drop_points() {
+        let mut game = new_game();
+
+        game.input(Input::SoftDrop);
+        game.input(Input::SoftDrop);
+        assert_eq!(game.score().points(), 2);
+
+        let rows = game.ghost_position().y() - game.position().y();
+        game.input(Input::HardDrop);
+        assert_eq!(game.score().points(), 2 + 2 * rows as u64);
+    }
+
+    #[test]
+    fn gravity_locks() {
+        let mut game = new_game();
+
+        game.update(Duration::from_secs(60));
+
+        assert!(game
+            .drain_events()
+            .any(|event| matches!(event, Event::Locked(_))));
+    }
+
+    #[test]
+    fn top_out() {
+        let mut game = new_game();
+
+        // Stacking in the middle never completes a line, so this has to top out
+        for _ in 0..100 {
+            game.input(Input::HardDrop);
+        }
+
+        assert!(game.is_over());
+        assert_eq!(
+            game.drain_events()
+                .filter(|event| *event == Event::GameOver)
+                .count(),
+            1
+        );
+
+        // Nothing moves anymore
+        let board = game.board().clone();
+        game.input(Input::HardDrop);
+        game.update(Duration::from_secs(60));
+        assert_eq!(game.board(), &board);
+    }
+
+    #[test]
+    fn same_seed_same_pieces() {
+        let mut a = Game::new(Config::default(), SevenBag::new(42));
+        let mut b = Game::new(Config::default(), SevenBag::new(42));
+
+        for _ in 0..10 {
+            assert_eq!(a.piece(), b.piece());
+            a.input(Input::HardDrop);
+            b.input(Input::HardDrop);
+        }
+        assert_eq!(a.board(), b.board());
+    }
+
+    #[test]
+    fn hold() {
+        let mut game = new_game();
+        let first = game.piece().id();
+
+        game.input(Input::Rotate(Rotation::Clockwise));
+        game.input(Input::MoveLeft);
+        game.input(Input::Hold);
+
+        assert_eq!(game.hold(), Some(first));
+        assert!(!game.can_hold());
+        let second = game.piece().id();
+
+        // Only once per piece
+        game.input(Input::Hold);
+        assert_eq!(game.hold(), Some(first));
+        assert_eq!(game.piece().id(), second);
+
+        game.input(Input::HardDrop);
+        assert!(game.can_hold());
+        let third = game.piece().id();
+
+        // Swapping gives back the held piece, fresh
+        game.input(Input::Hold);
+        assert_eq!(game.hold(), Some(third));
+        assert_eq!(game.piece(), &Piece::from(first));
+        assert_eq!(game.position(), new_game().position());
+    }
+
+    #[test]
+    fn queue() {
+        let mut game = new_game();
+        assert_eq!(game.queue().len(), Config::default().preview);
+
+        for _ in 0..10 {
+            let next = game.queue()[0];
+            game.input(Input::HardDrop);
+            assert_eq!(game.piece().id(), next);
+            assert_eq!(game.queue().len(), Config::default().preview);
+        }
+
+        // The preview length does not change the sequence
+        let mut long = Game::new(
+            Config {
+                preview: 12,
+                ..Config::default()
+            },
+            SevenBag::new(0),
+        );
+        let mut none = Game::new(
+            Config {
+                preview: 0,
+                ..Config::default()
+            },
+            SevenBag::new(0),
+        );
+        assert!(none.queue().is_empty());
+
+        for _ in 0..10 {
+            assert_eq!(long.piece(), none.piece());
+            long.input(Input::HardDrop);
+            none.input(Input::HardDrop);
+        }
+    }
+
+    fn locked(game: &mut Game) -> bool {
+        game.drain_events()
+            .any(|event| matches!(event, Event::Locked(_)))
+    }
+
+    // Soft drops all the way to the floor, without locking
+    fn land(game: &mut Game) {
+        for _ in 0..20 {
+            game.input(Input::SoftDrop);
+        }
+        assert!(!locked(game));
+    }
+
+    #[test]
+    fn lock_delay() {
+        let mut game = new_game();
+        let delay = game.config().lock_delay;
+        land(&mut game);
+
+        game.update(delay - Duration::from_millis(1));
+        assert!(!locked(&mut game));
+
+        game.update(Duration::from_millis(1));
+        assert!(locked(&mut game));
+
+        // No delay at all locks as soon as the piece lands
+        let mut game = Game::new(
+            Config {
+                lock_delay: Duration::ZERO,
+                ..Config::default()
+            },
+            SevenBag::new(0),
+        );
+        land(&mut game);
+        game.update(Duration::ZERO);
+        assert!(locked(&mut game));
+    }
+
+    #[test]
+    fn lock_delay_resets() {
+        let mut game = new_game();
+        let delay = game.config().lock_delay;
+        let almost = delay - Duration::from_millis(100);
+        land(&mut game);
+
+        // Every move on the ground restarts the delay
+        for i in 0..game.config().lock_resets {
+            game.update(almost);
+            game.input(if i % 2 == 0 {
+                Input::MoveLeft
+            } else {
+                Input::MoveRight
+            });
+            assert!(!locked(&mut game));
+        }
+
+        // Until there are no resets left
+        game.update(almost);
+        game.input(Input::MoveLeft);
+        game.update(Duration::from_millis(100));
+        assert!(locked(&mut game));
+    }
+
+    #[test]
+    fn lock_delay_in_the_air() {
+        let mut game = new_game();
+        let delay = game.config().lock_delay;
+
+        // Moving while falling does not use resets
+        for _ in 0..100 {
+            game.input(Input::MoveLeft);
+            game.input(Input::MoveRight);
+        }
+        land(&mut game);
+
+        game.update(delay - Duration::from_millis(1));
+        game.input(Input::MoveLeft);
+        game.update(delay - Duration::from_millis(1));
+        assert!(!locked(&mut game));
+    }
+
+    #[test]
+    fn replay() {
+        let mut game = new_game();
+        let inputs = [
+            Input::MoveLeft,
+            Input::Rotate(Rotation::Clockwise),
+            Input::SoftDrop,
+            Input::Hold,
+            Input::MoveRight,
+            Input::HardDrop,
+        ];
+
+        for i in 0..200 {
+            game.input(inputs[i % inputs.len()]);
+            game.update(Duration::from_millis(16 * (i as u64 % 5)));
+
+            if i % 50 == 0 {
+                game.receive_garbage(2, i as u8 % 10);
+            }
+        }
+        let steps = game.drain_steps().collect::<Vec<_>>();
+
+        let mut replayed = new_game();
+        for step in steps {
+            replayed.step(step);
+        }
+
+        assert_eq!(replayed.board(), game.board());
+        assert_eq!(replayed.board().checksum(), game.board().checksum());
+        assert_eq!(replayed.score(), game.score());
+        assert_eq!(replayed.piece(), game.piece());
+
+        assert_ne!(game.board().checksum(), Board::default().checksum());
+        assert_eq!(game.drain_steps().count(), 0);
+    }
+}
@@ -0,0 +1,157 @@
+#[cfg(test)]
+mod tests {
+    use tetris::*;
+
+    fn count(board: &Board, kind: Cell) -> usize {
+        board.iter().filter(|(cell, _)| *cell == Some(kind)).count()
+    }
+
+    // Seed of a game whose second piece is an I
+    fn i_second() -> u64 {
+        (0..)
+            .find(|seed| {
+                let mut bag = SevenBag::new(*seed);
+                bag.next();
+                bag.next() == PieceId::I
+            })
+            .unwrap()
+    }
+
+    // Drops the active piece against the right wall
+    fn drop_right(game: &mut Game) {
+        for _ in 0..10 {
+            game.input(Input::MoveRight);
+        }
+        game.input(Input::HardDrop);
+    }
+
+    #[test]
+    fn attack_table() {
+        assert_eq!(attack(ClearKind::Lines(0), None, false, false), 0);
+        assert_eq!(attack(ClearKind::Lines(1), Some(0), false, false), 0);
+        assert_eq!(attack(ClearKind::Lines(2), Some(0), false, false), 1);
+        assert_eq!(attack(ClearKind::Lines(4), Some(0), false, false), 4);
+        assert_eq!(attack(ClearKind::TSpin(2), Some(0), false, false), 4);
+        assert_eq!(attack(ClearKind::TSpinMini(1), Some(0), false, false), 0);
+
+        // Back to back only counts for difficult clears
+        assert_eq!(attack(ClearKind::Lines(4), Some(0), true, false), 5);
+        assert_eq!(attack(ClearKind::Lines(3), Some(0), true, false), 2);
+
+        // Combos
+        assert_eq!(attack(ClearKind::Lines(1), Some(2), false, false), 1);
+        assert_eq!(attack(ClearKind::Lines(1), Some(100), false, false), 5);
+
+        assert_eq!(attack(ClearKind::Lines(1), Some(0), false, true), 10);
+    }
+
+    #[test]
+    fn add_garbage() {
+        let mut board = Board::default();
+        let (piece, position) = (Piece::from(PieceId::O), Position::from((0, 19)));
+        board.place_at(&piece, &position).unwrap();
+
+        assert!(board.add_garbage(2, 3));
+        assert_eq!(board.row_mask(19), Some(0b1111110111));
+        assert_eq!(board.row_mask(18), Some(0b1111110111));
+        assert_eq!(board.row_mask(17), Some(0b11));
+        assert_eq!(count(&board, Cell::Garbage), 18);
+
+        // Pushing the stack out of the top
+        assert!(!board.add_garbage(18, 0));
+    }
+
+    #[test]
+    fn delay() {
+        let mut game = Game::new(Config::default(), SevenBag::new(0));
+        game.receive_garbage(2, 0);
+
+        // Too early
+        drop_right(&mut game);
+        assert_eq!(count(game.board(), Cell::Garbage), 0);
+
+        game.update(game.config().garbage_delay);
+        assert!(game.garbage()[0].is_ready());
+
+        drop_right(&mut game);
+        assert_eq!(count(game.board(), Cell::Garbage), 18);
+        assert!(game.garbage().is_empty());
+    }
+
+    #[test]
+    fn cancelling() {
+        let clear_double = |incoming: u32| {
+            let mut game = Game::new(Config::default(), SevenBag::new(i_second()));
+            game.receive_garbage(2, 0);
+            game.update(game.config().garbage_delay);
+            drop_right(&mut game);
+            game.receive_garbage(incoming, 5);
+            game.drain_events().for_each(drop);
+
+            // Vertical I in the holes
+            game.input(Input::Rotate(Rotation::CounterClockwise));
+            for _ in 0..10 {
+                game.input(Input::MoveLeft);
+            }
+            game.input(Input::HardDrop);
+
+            let events = game.drain_events().collect::<Vec<_>>();
+            assert!(
+                events
+                    .iter()
+                    .any(|event| matches!(event, Event::LinesCleared(lines) if lines.len() == 2))
+            );
+            (game, events)
+        };
+
+        // A double sends 1 line, eaten by the incoming garbage
+        let (game, events) = clear_double(3);
+        assert!(!events.iter().any(|event| matches!(event, Event::Attack(_))));
+        assert_eq!(game.garbage()[0].lines, 2);
+
+        // Without anything incoming, it goes out
+        let (game, events) = clear_double(0);
+        assert!(events.contains(&Event::Attack(1)));
+        assert!(game.garbage().is_empty());
+    }
+
+    #[test]
+    fn top_out() {
+        let mut game = Game::new(Config::default(), SevenBag::new(0));
+        game.receive_garbage(20, 0);
+        game.update(game.config().garbage_delay);
+
+        for _ in 0..3 {
+            drop_right(&mut game);
+        }
+
+        assert!(game.is_over());
+        assert!(game.drain_events().any(|event| event == Event::GameOver));
+    }
+
+    // Garbage is the quickest way to get lines to clear
+    #[test]
+    fn line_goal() {
+        let config = Config {
+            line_goal: Some(2),
+            ..Default::default()
+        };
+        let mut game = Game::new(config, SevenBag::new(i_second()));
+        game.receive_garbage(2, 0);
+        game.update(game.config().garbage_delay);
+        drop_right(&mut game);
+        assert!(!game.is_over());
+
+        game.input(Input::Rotate(Rotation::CounterClockwise));
+        for _ in 0..10 {
+            game.input(Input::MoveLeft);
+        }
+        game.input(Input::HardDrop);
+
+        assert_eq!(game.state(), State::Finished);
+        assert!(game.is_over());
+        let events = game.drain_events().collect::<Vec<_>>();
+        assert!(events.contains(&Event::Finished));
+        assert!(!events.contains(&Event::GameOver));
+    }
+}
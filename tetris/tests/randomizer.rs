@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use tetris::*;
+
+    fn sequence(randomizer: &mut impl Randomizer, len: usize) -> Vec<PieceId> {
+        (0..len).map(|_| randomizer.next()).collect()
+    }
+
+    #[test]
+    fn seven_bag() {
+        let mut bag = SevenBag::new(0);
+
+        for _ in 0..100 {
+            let mut pieces = sequence(&mut bag, 7);
+            let mut all = PieceId::ALL.to_vec();
+
+            pieces.sort_by_key(|id| *id as u8);
+            all.sort_by_key(|id| *id as u8);
+            assert_eq!(pieces, all);
+        }
+    }
+
+    #[test]
+    fn seeded() {
+        assert_eq!(
+            sequence(&mut SevenBag::new(1234), 70),
+            sequence(&mut SevenBag::new(1234), 70)
+        );
+        assert_ne!(
+            sequence(&mut SevenBag::new(1234), 70),
+            sequence(&mut SevenBag::new(4321), 70)
+        );
+
+        assert_eq!(
+            sequence(&mut Uniform::new(1234), 70),
+            sequence(&mut Uniform::new(1234), 70)
+        );
+        assert_eq!(
+            sequence(&mut History::new(1234), 70),
+            sequence(&mut History::new(1234), 70)
+        );
+    }
+
+    #[test]
+    fn uniform_uses_every_piece() {
+        let pieces = sequence(&mut Uniform::new(0), 1000);
+
+        for id in PieceId::ALL {
+            assert!(pieces.contains(&id), "{id:?} never showed up");
+        }
+    }
+
+    #[test]
+    fn history() {
+        let repeats = |pieces: Vec<PieceId>| pieces.windows(2).filter(|w| w[0] == w[1]).count();
+
+        let mut history_repeats = 0;
+        let mut uniform_repeats = 0;
+
+        for seed in 0..100 {
+            let pieces = sequence(&mut History::new(seed), 100);
+
+            assert!(![PieceId::S, PieceId::Z, PieceId::O].contains(&pieces[0]));
+
+            history_repeats += repeats(pieces);
+            uniform_repeats += repeats(sequence(&mut Uniform::new(seed), 100));
+        }
+
+        // Rerolls make repeats a lot rarer than the 1 in 7 of the uniform randomizer
+        assert!(
+            history_repeats * 3 < uniform_repeats,
+            "{history_repeats} repeats vs {uniform_repeats}"
+        );
+    }
+}
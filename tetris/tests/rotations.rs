@@ -36,12 +36,66 @@ mod tests {
         assert_debug_snapshot!(draw_piece(&piece));
 
         for _ in 0..rotations {
-            piece.rotate();
+            piece.rotate(Rotation::Clockwise);
 
             assert_debug_snapshot!(draw_piece(&piece));
         }
     }
 
+    // '#' for the stack, '@' for the piece
+    fn draw_board(board: &Board, piece: &Piece, position: &Position) -> String {
+        let mut out = format!(
+            "{:?} at ({}, {})\n\n",
+            piece.orientation(),
+            position.x(),
+            position.y()
+        );
+
+        let mut grid = vec![vec!['.'; 10]; 20];
+
+        for (cell, pos) in board.iter() {
+            if cell.is_some() {
+                grid[pos.y() as usize][pos.x() as usize] = '#';
+            }
+        }
+
+        for bit in piece.bits() {
+            let x = bit.x() + position.x() as i8;
+            let y = bit.y() + position.y() as i8;
+            if (0..20).contains(&y) && (0..10).contains(&x) {
+                grid[y as usize][x as usize] = '@';
+            }
+        }
+
+        for row in grid {
+            out.push_str(&format!("{}\n", row.iter().collect::<String>()));
+        }
+
+        out
+    }
+
+    // (x, y), 0 indexed
+    fn board_with(cells: impl IntoIterator<Item = (u8, u8)>) -> Board {
+        let mut board = Board::default();
+        for (x, y) in cells {
+            *board.get_mut_at(&Position::from((x + 1, y + 1))).unwrap() = Some(Cell::Piece(PieceId::O));
+        }
+        board
+    }
+
+    fn rotated(mut piece: Piece, rotation: Rotation) -> Piece {
+        piece.rotate(rotation);
+        piece
+    }
+
+    fn test_kick(board: &Board, piece: Piece, position: Position, rotation: Rotation) {
+        assert_debug_snapshot!(draw_board(board, &piece, &position));
+
+        let (piece, position, _) = board.rotate_piece(&piece, &position, rotation).unwrap();
+
+        assert_debug_snapshot!(draw_board(board, &piece, &position));
+    }
+
     #[test]
     fn test() {
         // Theses take snapshots of results, I havn't taken the time to verify them all atm
@@ -53,4 +107,130 @@ mod tests {
         test_piece(Piece::from(PieceId::L), 4);
         test_piece(Piece::from(PieceId::J), 4);
     }
+
+    #[test]
+    fn orientations() {
+        for id in [
+            PieceId::I,
+            PieceId::O,
+            PieceId::T,
+            PieceId::S,
+            PieceId::Z,
+            PieceId::L,
+            PieceId::J,
+        ] {
+            let spawn = Piece::from(id);
+
+            let mut piece = spawn.clone();
+            piece.rotate(Rotation::Clockwise);
+            assert_eq!(piece.orientation(), Orientation::Right);
+            piece.rotate(Rotation::CounterClockwise);
+            assert_eq!(piece, spawn);
+
+            piece.rotate(Rotation::Half);
+            assert_eq!(piece.orientation(), Orientation::Two);
+            assert_eq!(
+                piece,
+                rotated(
+                    rotated(spawn.clone(), Rotation::Clockwise),
+                    Rotation::Clockwise
+                )
+            );
+
+            piece.rotate(Rotation::CounterClockwise);
+            assert_eq!(piece.orientation(), Orientation::Right);
+        }
+    }
+
+    #[test]
+    fn kicks() {
+        // T against the left wall, pushed right
+        test_kick(
+            &Board::default(),
+            rotated(Piece::from(PieceId::T), Rotation::Clockwise),
+            Position::from((0, 10)),
+            Rotation::Clockwise,
+        );
+
+        // Vertical I against the right wall, pushed left
+        test_kick(
+            &Board::default(),
+            rotated(Piece::from(PieceId::I), Rotation::Clockwise),
+            Position::from((8, 10)),
+            Rotation::CounterClockwise,
+        );
+
+        // T resting on the floor, kicked up
+        test_kick(
+            &Board::default(),
+            Piece::from(PieceId::T),
+            Position::from((4, 19)),
+            Rotation::Clockwise,
+        );
+
+        // T against a column of the stack, pushed right
+        test_kick(
+            &board_with((15..20).map(|y| (3, y))),
+            rotated(Piece::from(PieceId::T), Rotation::Clockwise),
+            Position::from((4, 17)),
+            Rotation::Clockwise,
+        );
+
+        // T spin triple, only the last kick fits
+        test_kick(
+            &board_with(
+                (16..20)
+                    .flat_map(|y| (0..10).map(move |x| (x, y)))
+                    .filter(|cell| ![(4, 16), (4, 17), (4, 18), (5, 17)].contains(cell))
+                    .chain([(4, 14)]),
+            ),
+            Piece::from(PieceId::T),
+            Position::from((5, 15)),
+            Rotation::Clockwise,
+        );
+
+        // Vertical I in its own column of the stack, pushed up
+        test_kick(
+            &board_with(
+                (17..20)
+                    .flat_map(|y| (0..10).map(move |x| (x, y)))
+                    .filter(|(x, _)| *x != 5),
+            ),
+            rotated(Piece::from(PieceId::I), Rotation::Clockwise),
+            Position::from((4, 17)),
+            Rotation::Clockwise,
+        );
+
+        // Half turn of an S resting on the floor, kicked up
+        test_kick(
+            &Board::default(),
+            Piece::from(PieceId::S),
+            Position::from((4, 19)),
+            Rotation::Half,
+        );
+    }
+
+    #[test]
+    fn no_room() {
+        // Vertical I at the bottom of a 1 wide well, with every kick blocked
+        let board = board_with(
+            (10..20)
+                .flat_map(|y| (0..10).map(move |x| (x, y)))
+                .filter(|(x, _)| *x != 5),
+        );
+        let piece = rotated(Piece::from(PieceId::I), Rotation::Clockwise);
+        let position = Position::from((4, 17));
+
+        assert!(board.can_place_at(&piece, &position));
+        assert!(
+            board
+                .rotate_piece(&piece, &position, Rotation::Clockwise)
+                .is_none()
+        );
+        assert!(
+            board
+                .rotate_piece(&piece, &position, Rotation::CounterClockwise)
+                .is_none()
+        );
+    }
 }
@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use tetris::*;
+
+    fn clear(kind: ClearKind) -> LockResult {
+        LockResult {
+            lines: vec![0; kind.lines() as usize],
+            kind,
+            perfect_clear: false,
+        }
+    }
+
+    fn perfect(kind: ClearKind) -> LockResult {
+        LockResult {
+            perfect_clear: true,
+            ..clear(kind)
+        }
+    }
+
+    #[test]
+    fn clears() {
+        for (kind, points) in [
+            (ClearKind::Lines(1), 100),
+            (ClearKind::Lines(2), 300),
+            (ClearKind::Lines(3), 500),
+            (ClearKind::Lines(4), 800),
+            (ClearKind::TSpinMini(0), 100),
+            (ClearKind::TSpinMini(1), 200),
+            (ClearKind::TSpin(0), 400),
+            (ClearKind::TSpin(1), 800),
+            (ClearKind::TSpin(2), 1200),
+            (ClearKind::TSpin(3), 1600),
+        ] {
+            let mut score = Score::default();
+            assert_eq!(score.lock(&clear(kind)), points);
+
+            // Scaled by the level
+            let mut score = Score::new(3);
+            assert_eq!(score.lock(&clear(kind)), points * 3);
+        }
+    }
+
+    #[test]
+    fn drops() {
+        let mut score = Score::default();
+        score.soft_drop(3);
+        score.hard_drop(5);
+
+        assert_eq!(score.points(), 13);
+    }
+
+    #[test]
+    fn back_to_back() {
+        let mut score = Score::default();
+
+        assert_eq!(score.lock(&clear(ClearKind::Lines(4))), 800);
+        // Locks without lines do not break it, they only break combos
+        score.lock(&clear(ClearKind::Lines(0)));
+        assert_eq!(score.lock(&clear(ClearKind::Lines(4))), 1200);
+        assert!(score.back_to_back());
+
+        // T-spins keep it going
+        score.lock(&clear(ClearKind::Lines(0)));
+        assert_eq!(score.lock(&clear(ClearKind::TSpin(2))), 1800);
+
+        score.lock(&clear(ClearKind::Lines(0)));
+        score.lock(&clear(ClearKind::Lines(1)));
+        score.lock(&clear(ClearKind::Lines(0)));
+        assert!(!score.back_to_back());
+
+        // Those lines went up a level
+        assert_eq!(score.level(), 2);
+        assert_eq!(score.lock(&clear(ClearKind::Lines(4))), 1600);
+    }
+
+    #[test]
+    fn combo() {
+        let mut score = Score::default();
+
+        assert_eq!(score.lock(&clear(ClearKind::Lines(1))), 100);
+        assert_eq!(score.lock(&clear(ClearKind::Lines(1))), 150);
+        assert_eq!(score.lock(&clear(ClearKind::Lines(2))), 400);
+        assert_eq!(score.combo(), Some(2));
+
+        score.lock(&clear(ClearKind::Lines(0)));
+        assert_eq!(score.combo(), None);
+        assert_eq!(score.lock(&clear(ClearKind::Lines(1))), 100);
+    }
+
+    #[test]
+    fn perfect_clear() {
+        let mut score = Score::default();
+        assert_eq!(score.lock(&perfect(ClearKind::Lines(1))), 900);
+
+        let mut score = Score::default();
+        score.lock(&clear(ClearKind::Lines(4)));
+        score.lock(&clear(ClearKind::Lines(0)));
+        assert_eq!(score.lock(&perfect(ClearKind::Lines(4))), 1200 + 3200);
+    }
+
+    #[test]
+    fn levels() {
+        let mut score = Score::default();
+        assert_eq!(score.level(), 1);
+
+        for _ in 0..9 {
+            score.lock(&clear(ClearKind::Lines(1)));
+            score.lock(&clear(ClearKind::Lines(0)));
+        }
+        assert_eq!(score.level(), 1);
+
+        score.lock(&clear(ClearKind::Lines(1)));
+        assert_eq!(score.level(), 2);
+        assert_eq!(score.lines(), 10);
+
+        assert_eq!(Score::new(5).level(), 5);
+        assert_eq!(Score::new(0).level(), 1);
+    }
+
+    #[test]
+    fn gravity_curve() {
+        assert_eq!(gravity(1), Duration::from_secs(1));
+        assert_eq!(gravity(0), gravity(1));
+        assert_eq!(gravity(100), gravity(20));
+
+        for level in 1..20 {
+            assert!(gravity(level + 1) < gravity(level));
+        }
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use tetris::*;
+
+    // Every cell of the given (0 indexed) rows, except the holes
+    fn board_with(rows: &[(u8, &[u8])]) -> Board {
+        let mut board = Board::default();
+        for (y, holes) in rows {
+            for x in (0..10).filter(|x| !holes.contains(x)) {
+                *board.get_mut_at(&Position::from((x + 1, y + 1))).unwrap() = Some(Cell::Piece(PieceId::O));
+            }
+        }
+        board
+    }
+
+    fn t(rotation: Option<Rotation>) -> Piece {
+        let mut piece = Piece::from(PieceId::T);
+        if let Some(rotation) = rotation {
+            piece.rotate(rotation);
+        }
+        piece
+    }
+
+    // T pointing down into a slot, with an overhang on the left
+    fn tsd_board() -> Board {
+        let mut board = board_with(&[(18, &[3, 4, 5]), (19, &[4])]);
+        *board.get_mut_at(&Position::from((4, 18))).unwrap() = Some(Cell::Piece(PieceId::O));
+        board
+    }
+
+    #[test]
+    fn t_spin_double() {
+        let mut board = tsd_board();
+        let result = board
+            .place_and_clear(
+                &t(Some(Rotation::Half)),
+                &Position::from((4, 18)),
+                Some((0, 0)),
+            )
+            .unwrap();
+
+        assert_eq!(result.kind, ClearKind::TSpin(2));
+        assert!(result.kind.is_difficult());
+        assert_eq!(result.lines.len(), 2);
+    }
+
+    #[test]
+    fn needs_a_rotation() {
+        let mut board = tsd_board();
+        let result = board
+            .place_and_clear(&t(Some(Rotation::Half)), &Position::from((4, 18)), None)
+            .unwrap();
+
+        assert_eq!(result.kind, ClearKind::Lines(2));
+    }
+
+    #[test]
+    fn mini() {
+        // T pointing up at the bottom, the floor fills the back corners and only one front corner is filled
+        let board = board_with(&[(18, &[4, 5, 6, 7, 8, 9]), (19, &[3, 4, 5])]);
+        let position = Position::from((4, 19));
+
+        let result = board
+            .clone()
+            .place_and_clear(&t(None), &position, Some((0, 0)))
+            .unwrap();
+        assert_eq!(result.kind, ClearKind::TSpinMini(1));
+
+        // Unless it got there with the last kick
+        let result = board
+            .clone()
+            .place_and_clear(&t(None), &position, Some((1, 2)))
+            .unwrap();
+        assert_eq!(result.kind, ClearKind::TSpin(1));
+    }
+
+    #[test]
+    fn not_enough_corners() {
+        let mut board = board_with(&[(19, &[3, 4, 5])]);
+        let result = board
+            .place_and_clear(&t(None), &Position::from((4, 19)), Some((0, 0)))
+            .unwrap();
+
+        // The floor only gives two
+        assert_eq!(result.kind, ClearKind::Lines(1));
+    }
+
+    #[test]
+    fn only_t() {
+        let mut board = board_with(&[(18, &[3, 4, 5, 6, 7, 8, 9]), (19, &[3, 4, 5, 6])]);
+        let result = board
+            .place_and_clear(
+                &Piece::from(PieceId::I),
+                &Position::from((4, 19)),
+                Some((0, 0)),
+            )
+            .unwrap();
+
+        assert_eq!(result.kind, ClearKind::Lines(1));
+    }
+}